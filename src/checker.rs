@@ -0,0 +1,777 @@
+use std::collections::HashMap;
+
+use crate::{
+    parser::{parse, TypeAnnotation},
+    tokenizer::{tokenize_with_spans, Span},
+    ASTNode, Error, FunctionArity, Pattern, Type,
+};
+
+/// A type mismatch found by `check`, at the call or annotation where it was
+/// found.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mismatch {
+    pub message: String,
+    pub span: Span,
+}
+
+/// What the checker knows about one arity of a function. `None` stands for
+/// a type it doesn't know, which matches every type.
+#[derive(Clone)]
+struct ArityType {
+    parameters: Vec<Option<Type>>,
+    minimum: usize,
+    maximum: Option<usize>,
+    /// The type of the arguments past `parameters`, for variadic builtins.
+    rest: Option<Type>,
+    /// Whether `:name value` pairs are keyword arguments rather than values.
+    keywords: bool,
+    return_type: Option<Type>,
+}
+
+impl ArityType {
+    fn builtin(parameters: Vec<Option<Type>>, return_type: Option<Type>) -> Self {
+        Self {
+            minimum: parameters.len(),
+            maximum: Some(parameters.len()),
+            parameters,
+            rest: None,
+            keywords: false,
+            return_type,
+        }
+    }
+
+    fn variadic_builtin(minimum: usize, rest: Option<Type>, return_type: Option<Type>) -> Self {
+        Self {
+            parameters: Vec::new(),
+            minimum,
+            maximum: None,
+            rest,
+            keywords: false,
+            return_type,
+        }
+    }
+
+    fn accepts(&self, argument_count: usize) -> bool {
+        argument_count >= self.minimum
+            && self.maximum.is_none_or(|maximum| argument_count <= maximum)
+    }
+
+    fn parameter_type(&self, index: usize) -> Option<&Type> {
+        match self.parameters.get(index) {
+            Some(parameter_type) => parameter_type.as_ref(),
+            None => self.rest.as_ref(),
+        }
+    }
+}
+
+enum Binding {
+    Value(Option<Type>),
+    Function(Vec<ArityType>),
+}
+
+/// The types of the builtins the checker knows about.
+fn builtin_arities(name: &str) -> Option<Vec<ArityType>> {
+    let number = Some(Type::Number);
+
+    let arity = match name {
+        "add" | "subtract" | "multiply" | "divide" => {
+            ArityType::variadic_builtin(1, number.clone(), number)
+        }
+        "remainder" => ArityType::builtin(vec![number.clone(), number.clone()], number),
+        "is-less" | "is-greater" => ArityType::variadic_builtin(1, None, Some(Type::Boolean)),
+        "is-nil" | "is-empty" => ArityType::builtin(vec![None], Some(Type::Boolean)),
+        "is-equal" => ArityType::variadic_builtin(1, None, Some(Type::Boolean)),
+        "append" => ArityType::builtin(vec![Some(Type::List), None], Some(Type::List)),
+        "str" => ArityType::variadic_builtin(0, None, Some(Type::String)),
+        "println" => ArityType::variadic_builtin(0, None, Some(Type::Nil)),
+        "parse-number" => ArityType::builtin(vec![Some(Type::String)], number),
+        "read-file" => ArityType::builtin(vec![Some(Type::String)], Some(Type::String)),
+        "type-of" => ArityType::builtin(vec![None], Some(Type::String)),
+        _ => return None,
+    };
+
+    return Some(vec![arity]);
+}
+
+/// The type an annotation names, or `None` for `Any`. Names that aren't
+/// builtin types name records.
+fn annotated_type(annotation: &TypeAnnotation) -> Option<Type> {
+    let annotated_type = match annotation.name.as_str() {
+        "Number" => Type::Number,
+        "String" => Type::String,
+        "List" => Type::List,
+        "LazySequence" => Type::LazySequence,
+        "Map" => Type::Map,
+        "Boolean" => Type::Boolean,
+        "Function" => Type::Function,
+        "Promise" => Type::Promise,
+        "Atom" => Type::Atom,
+        "Result" => Type::Result,
+        "Keyword" => Type::Keyword,
+        "Nil" => Type::Nil,
+        "Any" => return None,
+        name => Type::Record(name.to_string()),
+    };
+
+    return Some(annotated_type);
+}
+
+/// The types of an arity as declared by its annotations.
+fn declared_arity_type(arity: &FunctionArity) -> ArityType {
+    let parameter_list = &arity.parameter_list;
+    let parameter_type = |name: &str| {
+        parameter_list
+            .types
+            .iter()
+            .find(|(parameter, _)| parameter == name)
+            .and_then(|(_, annotation)| annotated_type(annotation))
+    };
+
+    let mut parameters = Vec::new();
+
+    for pattern in parameter_list.required.iter() {
+        parameters.push(match pattern {
+            Pattern::Identifier(name) => parameter_type(name),
+            Pattern::Sequence { .. } => Some(Type::List),
+            Pattern::Map { .. } => Some(Type::Map),
+        });
+    }
+
+    for (name, _) in parameter_list.optional.iter() {
+        parameters.push(parameter_type(name));
+    }
+
+    return ArityType {
+        parameters,
+        minimum: parameter_list.minimum_arity(),
+        maximum: parameter_list.maximum_arity(),
+        rest: None,
+        keywords: !parameter_list.keywords.is_empty(),
+        return_type: arity.return_type.as_ref().and_then(annotated_type),
+    };
+}
+
+/// The names a `match` pattern binds.
+fn pattern_names<'a>(pattern: &'a ASTNode, names: &mut Vec<&'a str>) {
+    match pattern {
+        ASTNode::Identifier(name) if name != "_" => names.push(name),
+        ASTNode::CallExpression(_, patterns, _) => {
+            for pattern in patterns.iter() {
+                pattern_names(pattern, names);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// The type of both branches if they agree.
+fn common_type(a: Option<Type>, b: Option<Type>) -> Option<Type> {
+    if a == b {
+        return a;
+    }
+
+    return None;
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Binding>>,
+    /// The variants of every sum type declared with `deftype`.
+    sum_types: HashMap<String, Vec<String>>,
+    mismatches: Vec<Mismatch>,
+}
+
+impl Checker {
+    fn define(&mut self, name: &str, binding: Binding) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), binding);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        return self.scopes.iter().rev().find_map(|scope| scope.get(name));
+    }
+
+    fn is_compatible(&self, expected: &Type, actual: &Type) -> bool {
+        match (expected, actual) {
+            (Type::Record(sum_type), Type::Record(variant)) if sum_type != variant => self
+                .sum_types
+                .get(sum_type)
+                .is_some_and(|variants| variants.contains(variant)),
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    fn infer(&mut self, node: &ASTNode) -> Option<Type> {
+        match node {
+            ASTNode::NumberLiteral(_) => Some(Type::Number),
+            ASTNode::BooleanLiteral(_) => Some(Type::Boolean),
+            ASTNode::NilLiteral => Some(Type::Nil),
+            ASTNode::StringLiteral(_) => Some(Type::String),
+            ASTNode::KeywordLiteral(_) => Some(Type::Keyword),
+
+            ASTNode::Identifier(name) => match self.lookup(name) {
+                Some(Binding::Value(value_type)) => value_type.clone(),
+                Some(Binding::Function(_)) => Some(Type::Function),
+                None => builtin_arities(name).map(|_| Type::Function),
+            },
+
+            ASTNode::ListExpression(nodes) => {
+                for node in nodes.iter() {
+                    self.infer(node);
+                }
+
+                Some(Type::List)
+            }
+
+            ASTNode::MapExpression(entries) => {
+                for (key, value) in entries.iter() {
+                    self.infer(key);
+                    self.infer(value);
+                }
+
+                Some(Type::Map)
+            }
+
+            ASTNode::MetadataExpression {
+                metadata,
+                expression,
+            } => {
+                self.infer(metadata);
+                self.infer(expression)
+            }
+
+            ASTNode::IfExpression {
+                expression,
+                when_true,
+                when_false,
+            } => {
+                self.infer(expression);
+                let when_true = self.infer(when_true);
+                let when_false = self.infer(when_false);
+
+                common_type(when_true, when_false)
+            }
+
+            ASTNode::FunctionDeclaration {
+                identifier,
+                arities,
+                ..
+            } => {
+                // Declare the function before checking its bodies, so that
+                // recursive calls are checked against its annotations.
+                let declared = arities.iter().map(declared_arity_type).collect();
+                self.define(identifier, Binding::Function(declared));
+
+                let inferred = self.infer_function(identifier, arities);
+                self.define(identifier, Binding::Function(inferred));
+
+                Some(Type::Function)
+            }
+
+            ASTNode::FunctionExpression { arities } => {
+                self.infer_function("fn", arities);
+                Some(Type::Function)
+            }
+
+            ASTNode::MethodDeclaration {
+                identifier,
+                dispatch_value,
+                arities,
+            } => {
+                self.infer(dispatch_value);
+                self.infer_function(identifier, arities);
+                Some(Type::Function)
+            }
+
+            ASTNode::TypeExtension { methods, .. } => {
+                for (name, arities) in methods.iter() {
+                    self.infer_function(name, arities);
+                }
+
+                Some(Type::Nil)
+            }
+
+            ASTNode::LetExpression { bindings, body } => self.infer_let(bindings, body, false),
+            ASTNode::TryLetExpression { bindings, body } => self.infer_let(bindings, body, true),
+
+            ASTNode::CallExpression(callee, arguments, span) => {
+                self.infer_call(callee, arguments, *span)
+            }
+        }
+    }
+
+    /// Checks the bodies of a function, returning the types of its arities
+    /// with the return types inferred where they aren't annotated.
+    fn infer_function(&mut self, name: &str, arities: &[FunctionArity]) -> Vec<ArityType> {
+        let mut arity_types = Vec::with_capacity(arities.len());
+
+        for arity in arities.iter() {
+            let mut arity_type = declared_arity_type(arity);
+            let parameter_list = &arity.parameter_list;
+
+            let mut scope = HashMap::new();
+
+            for name in parameter_list.names() {
+                scope.insert(name.to_string(), Binding::Value(None));
+            }
+
+            if let Some(Pattern::Identifier(rest)) = &parameter_list.rest {
+                scope.insert(rest.to_string(), Binding::Value(Some(Type::List)));
+            }
+
+            for (name, annotation) in parameter_list.types.iter() {
+                scope.insert(name.to_string(), Binding::Value(annotated_type(annotation)));
+            }
+
+            self.scopes.push(scope);
+
+            for condition in arity.conditions.pre.iter() {
+                self.infer(condition);
+            }
+
+            let body_type = self.infer(&arity.body);
+
+            // Postconditions see the return value as `%`.
+            self.scopes.push(HashMap::from([(
+                "%".to_string(),
+                Binding::Value(body_type.clone()),
+            )]));
+
+            for condition in arity.conditions.post.iter() {
+                self.infer(condition);
+            }
+
+            self.scopes.pop();
+            self.scopes.pop();
+
+            match &arity.return_type {
+                Some(annotation) => {
+                    if let (Some(expected), Some(actual)) = (annotated_type(annotation), &body_type)
+                    {
+                        if !self.is_compatible(&expected, actual) {
+                            self.mismatches.push(Mismatch {
+                                message: format!(
+                                    "expected {} to return {}, got {}",
+                                    name, expected, actual
+                                ),
+                                span: annotation.span,
+                            });
+                        }
+                    }
+                }
+                None => arity_type.return_type = body_type,
+            }
+
+            arity_types.push(arity_type);
+        }
+
+        return arity_types;
+    }
+
+    /// What `name` is bound to by `define` or `let`, keeping the arities of
+    /// functions so that calls to them are checked.
+    fn infer_binding(&mut self, name: &str, value: &ASTNode) -> Binding {
+        match value {
+            ASTNode::FunctionExpression { arities } => {
+                Binding::Function(self.infer_function(name, arities))
+            }
+            value => Binding::Value(self.infer(value)),
+        }
+    }
+
+    fn infer_let(
+        &mut self,
+        bindings: &[(Pattern, ASTNode)],
+        body: &[ASTNode],
+        is_try: bool,
+    ) -> Option<Type> {
+        self.scopes.push(HashMap::new());
+
+        for (pattern, value) in bindings.iter() {
+            match pattern {
+                // `try-let` binds the values of results, whose types aren't known.
+                Pattern::Identifier(name) if !is_try => {
+                    let binding = self.infer_binding(name, value);
+                    self.define(name, binding);
+                }
+                pattern => {
+                    self.infer(value);
+
+                    let mut names = Vec::new();
+                    pattern.collect_names(&mut names);
+
+                    for name in names {
+                        self.define(name, Binding::Value(None));
+                    }
+                }
+            }
+        }
+
+        let mut result = Some(Type::Nil);
+
+        for node in body.iter() {
+            result = self.infer(node);
+        }
+
+        self.scopes.pop();
+
+        return result;
+    }
+
+    /// Checks the special forms that declare names or don't evaluate their
+    /// arguments, returning `None` for calls of functions.
+    fn infer_special_form(&mut self, name: &str, arguments: &[ASTNode]) -> Option<Option<Type>> {
+        match (name, arguments) {
+            ("define", [ASTNode::Identifier(variable), value]) => {
+                let binding = self.infer_binding(variable, value);
+                let value_type = match &binding {
+                    Binding::Value(value_type) => value_type.clone(),
+                    Binding::Function(_) => Some(Type::Function),
+                };
+                self.define(variable, binding);
+
+                Some(value_type)
+            }
+
+            ("defrecord", [ASTNode::Identifier(record), ASTNode::ListExpression(fields)]) => {
+                let constructor = ArityType::builtin(
+                    vec![None; fields.len()],
+                    Some(Type::Record(record.to_string())),
+                );
+                self.define(record, Binding::Function(vec![constructor]));
+
+                Some(Some(Type::Function))
+            }
+
+            ("deftype", [ASTNode::Identifier(sum_type), variants @ ..]) => {
+                let mut variant_names = Vec::new();
+
+                for variant in variants.iter() {
+                    match variant {
+                        ASTNode::Identifier(variant) => {
+                            let variant_type = Type::Record(variant.to_string());
+                            self.define(variant, Binding::Value(Some(variant_type)));
+                            variant_names.push(variant.to_string());
+                        }
+                        ASTNode::CallExpression(callee, fields, _) => {
+                            if let ASTNode::Identifier(variant) = callee.as_ref() {
+                                let constructor = ArityType::builtin(
+                                    vec![None; fields.len()],
+                                    Some(Type::Record(variant.to_string())),
+                                );
+                                self.define(variant, Binding::Function(vec![constructor]));
+                                variant_names.push(variant.to_string());
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+
+                self.sum_types.insert(sum_type.to_string(), variant_names);
+
+                Some(Some(Type::Nil))
+            }
+
+            ("defmulti", [ASTNode::Identifier(multi_method), dispatch]) => {
+                self.infer(dispatch);
+                self.define(multi_method, Binding::Value(Some(Type::Function)));
+
+                Some(Some(Type::Function))
+            }
+
+            ("defprotocol", [ASTNode::Identifier(_), signatures @ ..]) => {
+                for signature in signatures.iter() {
+                    if let ASTNode::CallExpression(callee, _, _) = signature {
+                        if let ASTNode::Identifier(method) = callee.as_ref() {
+                            self.define(method, Binding::Value(Some(Type::Function)));
+                        }
+                    }
+                }
+
+                Some(Some(Type::List))
+            }
+
+            ("match", [subject, clauses @ ..]) => {
+                self.infer(subject);
+
+                let mut result: Option<Option<Type>> = None;
+
+                for clause in clauses.chunks(2) {
+                    let [pattern, body] = clause else {
+                        break;
+                    };
+
+                    let mut names = Vec::new();
+                    pattern_names(pattern, &mut names);
+
+                    self.scopes.push(
+                        names
+                            .into_iter()
+                            .map(|name| (name.to_string(), Binding::Value(None)))
+                            .collect(),
+                    );
+                    let body_type = self.infer(body);
+                    self.scopes.pop();
+
+                    result = Some(match result {
+                        Some(previous) => common_type(previous, body_type),
+                        None => body_type,
+                    });
+                }
+
+                Some(result.flatten())
+            }
+
+            ("catch", [ASTNode::Identifier(binding), handler @ ..]) => {
+                self.scopes.push(HashMap::new());
+                self.define(binding, Binding::Value(None));
+
+                let mut result = Some(Type::Nil);
+
+                for node in handler.iter() {
+                    result = self.infer(node);
+                }
+
+                self.scopes.pop();
+
+                Some(result)
+            }
+
+            ("ns" | "require" | "import", _) => Some(Some(Type::Nil)),
+
+            _ => None,
+        }
+    }
+
+    fn infer_call(&mut self, callee: &ASTNode, arguments: &[ASTNode], span: Span) -> Option<Type> {
+        let (name, arities) = match callee {
+            ASTNode::Identifier(name) => {
+                if self.lookup(name).is_none() {
+                    if let Some(result) = self.infer_special_form(name, arguments) {
+                        return result;
+                    }
+                }
+
+                let arities = match self.lookup(name) {
+                    Some(Binding::Function(arities)) => Some(arities.clone()),
+                    Some(Binding::Value(_)) => None,
+                    None => builtin_arities(name),
+                };
+
+                (name.as_str(), arities)
+            }
+
+            callee => {
+                self.infer(callee);
+                ("fn", None)
+            }
+        };
+
+        let takes_keyword_arguments = arities
+            .iter()
+            .flatten()
+            .any(|arity_type| arity_type.keywords);
+
+        let mut argument_types = Vec::new();
+        let mut nodes = arguments.iter();
+
+        while let Some(node) = nodes.next() {
+            match node {
+                ASTNode::KeywordLiteral(_) if takes_keyword_arguments => {
+                    if let Some(value) = nodes.next() {
+                        self.infer(value);
+                    }
+                }
+                node => argument_types.push(self.infer(node)),
+            }
+        }
+
+        let arities = arities?;
+        let arity_type = arities
+            .iter()
+            .find(|arity_type| arity_type.accepts(argument_types.len()))?;
+
+        for (index, argument_type) in argument_types.iter().enumerate() {
+            if let (Some(expected), Some(actual)) =
+                (arity_type.parameter_type(index), argument_type)
+            {
+                if !self.is_compatible(expected, actual) {
+                    self.mismatches.push(Mismatch {
+                        message: format!(
+                            "expected {} for argument {} of {}, got {}",
+                            expected,
+                            index + 1,
+                            name,
+                            actual
+                        ),
+                        span,
+                    });
+                }
+            }
+        }
+
+        return arity_type.return_type.clone();
+    }
+}
+
+/// Infers what it can of the types of the expressions without evaluating
+/// them, and returns the mismatches with the types annotated on functions
+/// and those of builtins. Expressions of unknown types are never reported.
+pub fn check(expressions: &[ASTNode]) -> Vec<Mismatch> {
+    let mut checker = Checker {
+        scopes: vec![HashMap::new()],
+        sum_types: HashMap::new(),
+        mismatches: Vec::new(),
+    };
+
+    // Declare the top-level functions first, so that calls to functions
+    // declared further down are checked too.
+    for expression in expressions.iter() {
+        let expression = match expression {
+            ASTNode::MetadataExpression { expression, .. } => expression,
+            expression => expression,
+        };
+
+        if let ASTNode::FunctionDeclaration {
+            identifier,
+            arities,
+            ..
+        } = expression
+        {
+            let declared = arities.iter().map(declared_arity_type).collect();
+            checker.define(identifier, Binding::Function(declared));
+        }
+    }
+
+    for expression in expressions.iter() {
+        checker.infer(expression);
+    }
+
+    return checker.mismatches;
+}
+
+/// Parses and checks a program, as `risp check` does.
+pub fn check_source(source: &str) -> Result<Vec<Mismatch>, Error> {
+    let expressions = parse(&mut tokenize_with_spans(source).into())?;
+
+    return Ok(check(&expressions));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        check_source(source)
+            .unwrap()
+            .into_iter()
+            .map(|mismatch| mismatch.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_annotated_parameters() {
+        assert_eq!(
+            check_source(
+                "(defn add-two [a : Number] : Number (add a 2))
+                 (add-two 1)
+                 (add-two \"one\")"
+            ),
+            Ok(vec![Mismatch {
+                message: "expected Number for argument 1 of add-two, got String".to_string(),
+                span: Span {
+                    line: 3,
+                    column: 18
+                },
+            }])
+        );
+    }
+
+    #[test]
+    fn test_annotated_return_types() {
+        assert_eq!(
+            check_source("(defn name [] : String 42)"),
+            Ok(vec![Mismatch {
+                message: "expected name to return String, got Number".to_string(),
+                span: Span {
+                    line: 1,
+                    column: 17
+                },
+            }])
+        );
+    }
+
+    #[test]
+    fn test_inferred_types() {
+        assert_eq!(
+            messages(
+                "(defn twice [a] (multiply a 2))
+                 (define greeting (str \"hello \" (twice 2)))
+                 (let [n (twice 3)
+                       half (fn [a : Number] (divide a 2))]
+                   (list (add greeting n) (half \"four\") (remainder (twice \"x\") 2)))"
+            ),
+            vec![
+                "expected Number for argument 1 of add, got String",
+                "expected Number for argument 1 of half, got String",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_functions_declared_later() {
+        assert_eq!(
+            messages(
+                "(defn main [] (greet 1))
+                 (defn greet [name : String] (str \"hello \" name))"
+            ),
+            vec!["expected String for argument 1 of greet, got Number"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_types_are_not_reported() {
+        assert!(messages(
+            "(defn f [a] (add a 1))
+             (defn g [a : Any] a)
+             (f \"x\")
+             (f (g \"x\"))
+             (let [add (fn [a] a)] (add \"x\"))
+             (defn h [a : Number] a)
+             (match 1 n (h n))
+             (try (h 1) (catch e (h e)))"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_records_and_variants() {
+        assert_eq!(
+            messages(
+                "(defrecord Point [x y])
+                 (deftype Shape (Circle r) Empty)
+                 (defn area [shape : Shape] : Number 0)
+                 (defn norm [point : Point] 0)
+                 (area (Circle 1))
+                 (area Empty)
+                 (norm (Point 1 2))
+                 (norm (Circle 1))
+                 (area (Point 1 2))"
+            ),
+            vec![
+                "expected Point for argument 1 of norm, got Circle",
+                "expected Shape for argument 1 of area, got Point",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        assert!(messages(
+            "(defn scale [a : Number &key (by 2)] (multiply a by))
+             (scale 1 :by 3)"
+        )
+        .is_empty());
+    }
+}
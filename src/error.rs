@@ -1,27 +1,425 @@
-use crate::{Token, Type};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-#[derive(Debug, PartialEq)]
+use crate::{tokenizer::Span, Token, Type, Value};
+
+/// An error raised while parsing or evaluating. The details are boxed to
+/// keep `Result`s small, since they are passed through every recursive call
+/// of the evaluator.
+#[derive(Clone)]
 pub struct Error {
+    details: Box<ErrorDetails>,
+}
+
+#[derive(Clone)]
+struct ErrorDetails {
     error_type: ErrorType,
+    /// Explains unexpected tokens and argument errors further, e.g.
+    /// `Expected a parameter list`.
+    message: String,
+    suggestion: Option<String>,
+    backtrace: Vec<CallFrame>,
+    source: Option<Arc<std::io::Error>>,
+}
+
+impl std::fmt::Debug for Error {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("Error")
+            .field("error_type", &self.details.error_type)
+            .field("suggestion", &self.details.suggestion)
+            .field("backtrace", &self.details.backtrace)
+            .finish()
+    }
+}
+
+/// `type error: expected List, got Number in call to car`, followed by a
+/// suggestion if there is one.
+impl std::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}", self.message())?;
+
+        if let Some(frame) = self.details.backtrace.first() {
+            write!(formatter, " in call to {}", frame.function)?;
+        }
+
+        if let Some(suggestion) = &self.details.suggestion {
+            write!(formatter, " (did you mean {}?)", suggestion)?;
+        }
+
+        return Ok(());
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.details
+            .source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        let mut result = Error::new("IO error", ErrorType::IOError(error.kind()));
+        result.details.source = Some(Arc::new(error));
+        return result;
+    }
+}
+
+/// Errors are equal if they are the same error, wherever they happened.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.details.error_type == other.details.error_type
+            && self.details.suggestion == other.details.suggestion
+    }
+}
+
+/// A problem found while evaluating that doesn't stop the program, e.g. a
+/// `match` that doesn't handle every variant of a type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub message: String,
+    pub span: Span,
+    /// The module the warning is about, or `None` for the file being run.
+    pub file: Option<PathBuf>,
+}
+
+/// A function call that was in progress when an error happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    pub function: String,
+    /// Where the function was called, or `None` if it was called by a
+    /// builtin such as `map`.
+    pub span: Option<Span>,
+    /// The module the call was made in, or `None` for the file being run.
+    pub file: Option<PathBuf>,
+    /// The parameters the function was called with, e.g. `a=1, b=(1 2)`.
+    pub arguments: String,
 }
 
 impl Error {
-    pub fn new(_message: &str, error_type: ErrorType) -> Self {
-        return Self { error_type };
+    pub fn new(message: &str, error_type: ErrorType) -> Self {
+        return Self {
+            details: Box::new(ErrorDetails {
+                error_type,
+                message: message.to_string(),
+                suggestion: None,
+                backtrace: Vec::new(),
+                source: None,
+            }),
+        };
+    }
+
+    /// Describes the error without where it happened, e.g.
+    /// `syntax error: unexpected ']', expected a parameter list`.
+    pub fn message(&self) -> String {
+        let details = &self.details;
+
+        match &details.error_type {
+            ErrorType::UnexpectedToken(_) => format!(
+                "{}, {}",
+                details.error_type,
+                lowercase_first(&details.message)
+            ),
+            ErrorType::ArgumentError => format!(
+                "{}: {}",
+                details.error_type,
+                lowercase_first(&details.message)
+            ),
+            error_type => error_type.to_string(),
+        }
+    }
+
+    /// Attaches a "did you mean" hint, e.g. a similarly named variable.
+    pub fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.details.suggestion = suggestion;
+        return self;
+    }
+
+    pub fn error_type(&self) -> &ErrorType {
+        return &self.details.error_type;
+    }
+
+    pub fn suggestion(&self) -> Option<&str> {
+        return self.details.suggestion.as_deref();
+    }
+
+    /// The calls that were in progress when the error happened, innermost
+    /// first.
+    pub fn backtrace(&self) -> &[CallFrame] {
+        return &self.details.backtrace;
+    }
+
+    pub(crate) fn with_frame(mut self, function: &str, arguments: String) -> Self {
+        self.details.backtrace.push(CallFrame {
+            function: function.to_string(),
+            span: None,
+            file: None,
+            arguments,
+        });
+        return self;
+    }
+
+    /// Records that the calls made so far without a known file were made in
+    /// the module `file`.
+    pub(crate) fn in_file(mut self, file: &Path) -> Self {
+        for frame in self.details.backtrace.iter_mut() {
+            if frame.span.is_some() && frame.file.is_none() {
+                frame.file = Some(file.to_path_buf());
+            }
+        }
+        return self;
+    }
+
+    /// Records where the call of the outermost frame so far was made.
+    pub(crate) fn at_call_site(mut self, span: Span) -> Self {
+        if let Some(frame) = self.details.backtrace.last_mut() {
+            frame.span.get_or_insert(span);
+        }
+        return self;
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ErrorType {
     UnexpectedToken(Token),
     MissingToken,
     UndefinedFunction(String),
+    UndefinedVariable(String),
     ArgumentError,
-    NotAFunction(String),
-    TooFewArguments,
+    NotAFunction(Type),
+    TooFewArguments {
+        expected: usize,
+        given: usize,
+    },
+    TooManyArguments {
+        expected: usize,
+        given: usize,
+    },
+    /// A multi-arity function has no arity for the number of arguments given,
+    /// though it has ones for fewer and more. Every arity accepts from its
+    /// minimum to its maximum number of arguments, if it has one.
+    NoMatchingArity {
+        accepted: Vec<(usize, Option<usize>)>,
+        given: usize,
+    },
+    UnexpectedKeywordArgument(String),
     IOError(std::io::ErrorKind),
+    InvalidNumber(String),
     TypeError {
         expected_type: Type,
         actual_type: Type,
     },
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+    },
+    DestructuringError {
+        expected_length: usize,
+        actual_length: usize,
+    },
+    /// A value raised by `throw`.
+    Thrown(Value),
+    ModuleNotFound(String),
+    /// The chain of modules requiring each other, e.g. `a.rsp -> b.rsp ->
+    /// a.rsp`.
+    ModuleCycle(String),
+    /// A value no clause of a `match` matched.
+    NoMatch(Value),
+    /// A multimethod has no method for the dispatch value and no default.
+    NoMethod {
+        function: String,
+        dispatch_value: Value,
+    },
+    /// A `:pre` or `:post` condition of a function was false.
+    ContractViolation {
+        function: String,
+        condition: String,
+        postcondition: bool,
+    },
+    /// `yield` or `yield-from`, by name, evaluated where no generator can
+    /// suspend.
+    MisplacedYield(String),
+}
+
+impl ErrorType {
+    /// The name risp programs see in the `kind` field of a caught error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ErrorType::UnexpectedToken(_) => "unexpected-token",
+            ErrorType::MissingToken => "missing-token",
+            ErrorType::UndefinedFunction(_) => "undefined-function",
+            ErrorType::UndefinedVariable(_) => "undefined-variable",
+            ErrorType::ArgumentError => "argument-error",
+            ErrorType::NotAFunction(_) => "not-a-function",
+            ErrorType::TooFewArguments { .. } => "too-few-arguments",
+            ErrorType::TooManyArguments { .. } => "too-many-arguments",
+            ErrorType::NoMatchingArity { .. } => "no-matching-arity",
+            ErrorType::UnexpectedKeywordArgument(_) => "unexpected-keyword-argument",
+            ErrorType::IOError(_) => "io-error",
+            ErrorType::InvalidNumber(_) => "invalid-number",
+            ErrorType::TypeError { .. } => "type-error",
+            ErrorType::IndexOutOfBounds { .. } => "index-out-of-bounds",
+            ErrorType::DestructuringError { .. } => "destructuring-error",
+            ErrorType::Thrown(_) => "thrown",
+            ErrorType::ModuleNotFound(_) => "module-not-found",
+            ErrorType::ModuleCycle(_) => "module-cycle",
+            ErrorType::NoMatch(_) => "no-match",
+            ErrorType::NoMethod { .. } => "no-method",
+            ErrorType::ContractViolation { .. } => "contract-violation",
+            ErrorType::MisplacedYield(_) => "misplaced-yield",
+        }
+    }
+}
+
+/// A precise description of the error, e.g. `type error: expected List, got
+/// Number`.
+impl std::fmt::Display for ErrorType {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorType::UnexpectedToken(token) => {
+                write!(formatter, "syntax error: unexpected '{}'", token)
+            }
+            ErrorType::MissingToken => write!(formatter, "syntax error: unexpected end of input"),
+            ErrorType::UndefinedFunction(name) => write!(formatter, "undefined function: {}", name),
+            ErrorType::UndefinedVariable(name) => write!(formatter, "undefined variable: {}", name),
+            ErrorType::ArgumentError => write!(formatter, "argument error"),
+            ErrorType::NotAFunction(actual_type) => write!(
+                formatter,
+                "type error: a value of type {} is not a function",
+                actual_type
+            ),
+            ErrorType::TooFewArguments { expected, given } => write!(
+                formatter,
+                "argument error: expected at least {}, got {}",
+                count_arguments(*expected),
+                given
+            ),
+            ErrorType::TooManyArguments { expected, given } => write!(
+                formatter,
+                "argument error: expected at most {}, got {}",
+                count_arguments(*expected),
+                given
+            ),
+            ErrorType::NoMatchingArity { accepted, given } => write!(
+                formatter,
+                "argument error: expected {} arguments, got {}",
+                describe_arities(accepted),
+                given
+            ),
+            ErrorType::UnexpectedKeywordArgument(name) => {
+                write!(
+                    formatter,
+                    "argument error: unexpected keyword argument :{}",
+                    name
+                )
+            }
+            ErrorType::IOError(kind) => write!(formatter, "io error: {}", kind),
+            ErrorType::InvalidNumber(string) => {
+                write!(formatter, "invalid number: {:?} is not a number", string)
+            }
+            ErrorType::TypeError {
+                expected_type,
+                actual_type,
+            } => write!(
+                formatter,
+                "type error: expected {}, got {}",
+                expected_type, actual_type
+            ),
+            ErrorType::IndexOutOfBounds { index, length } => write!(
+                formatter,
+                "index out of bounds: index {} is out of bounds for length {}",
+                index, length
+            ),
+            ErrorType::DestructuringError {
+                expected_length,
+                actual_length,
+            } => write!(
+                formatter,
+                "destructuring error: expected {} values, got {}",
+                expected_length, actual_length
+            ),
+            ErrorType::Thrown(value) => {
+                write!(
+                    formatter,
+                    "uncaught exception: {}",
+                    value.to_display_string()
+                )
+            }
+            ErrorType::ModuleNotFound(module) => write!(formatter, "module not found: {}", module),
+            ErrorType::ModuleCycle(chain) => write!(formatter, "circular require: {}", chain),
+            ErrorType::NoMatch(value) => write!(
+                formatter,
+                "match error: no clause matches {}",
+                value.to_display_string()
+            ),
+            ErrorType::NoMethod {
+                function,
+                dispatch_value,
+            } => write!(
+                formatter,
+                "no method: {} has no method for {}",
+                function,
+                dispatch_value.to_display_string()
+            ),
+            ErrorType::ContractViolation {
+                function,
+                condition,
+                postcondition,
+            } => write!(
+                formatter,
+                "contract violation: {} {} of {} failed",
+                if *postcondition {
+                    "postcondition"
+                } else {
+                    "precondition"
+                },
+                condition,
+                function
+            ),
+            ErrorType::MisplacedYield(name) => write!(
+                formatter,
+                "misplaced yield: {} can only be used in the body of a generator, the branches of an if or the body of a let inside it",
+                name
+            ),
+        }
+    }
+}
+
+fn lowercase_first(message: &str) -> String {
+    let mut characters = message.chars();
+
+    match characters.next() {
+        Some(first) => first.to_lowercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
+
+/// Lists arities like `1, 2 to 3 or 5 or more`.
+fn describe_arities(accepted: &[(usize, Option<usize>)]) -> String {
+    let mut descriptions: Vec<String> = accepted
+        .iter()
+        .map(|arity| match arity {
+            (minimum, Some(maximum)) if minimum == maximum => minimum.to_string(),
+            (minimum, Some(maximum)) => format!("{} to {}", minimum, maximum),
+            (minimum, None) => format!("{} or more", minimum),
+        })
+        .collect();
+
+    return match descriptions.pop() {
+        Some(last) if descriptions.is_empty() => last,
+        Some(last) => format!("{} or {}", descriptions.join(", "), last),
+        None => String::new(),
+    };
+}
+
+fn count_arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_string(),
+        count => format!("{} arguments", count),
+    }
 }
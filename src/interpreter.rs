@@ -1,36 +1,147 @@
-mod environment;
+mod atoms;
+mod builtins;
+mod caching;
+mod call;
+mod docs;
+pub(crate) mod environment;
+mod exceptions;
+mod generators;
+mod lazy;
+mod matching;
+mod metadata;
+mod modules;
+mod multimethods;
+mod pattern;
+mod prelude;
+mod records;
+mod results;
+mod sequences;
 
 use std::{
-    collections::{HashMap, VecDeque},
-    ops::Deref,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
-use crate::{parser::parse, tokenize, value::Function, ASTNode, Error, ErrorType, Type, Value};
-use environment::EnvironmentStack;
+use crate::{
+    parser::{parse, Conditions, Pattern},
+    tokenizer::{tokenize_with_spans, Span},
+    value::{Function, Keyword, LazySequence, Map, Promise, Thunk},
+    ASTNode, Error, ErrorType, FunctionArity, ParameterList, Type, Value, Warning,
+};
+use builtins::{define_builtins, type_error};
+use call::KeywordArguments;
+use environment::{EnvironmentStack, Scope};
+use modules::{LoadingFile, Module};
+use pattern::bind_pattern;
 
 pub struct Interpreter {
     environment_stack: EnvironmentStack,
+    undefined_as_nil: bool,
+    /// Loaded modules by their canonical path.
+    modules: HashMap<PathBuf, Module>,
+    /// Environments of modules that failed to load, kept alive for the
+    /// functions they declared.
+    failed_modules: Vec<Scope>,
+    /// The files being evaluated, the innermost last.
+    loading: Vec<LoadingFile>,
+    /// The module the code being evaluated is in, or `None` for the file
+    /// being run.
+    current_file: Option<Rc<Path>>,
+    module_search_path: Vec<PathBuf>,
+    /// Warnings not yet taken with `take_warnings`.
+    warnings: Vec<Warning>,
+    /// Where the warnings raised so far were raised, to raise each one once.
+    warned_locations: Vec<(Option<PathBuf>, Span)>,
+    /// Whether the prelude modules can be required, which they can't in a
+    /// bare interpreter.
+    prelude: bool,
+    /// Whether the `:pre` and `:post` conditions of functions are checked.
+    check_contracts: bool,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
+    /// Creates an interpreter with the builtins and the prelude.
     pub fn new() -> Self {
+        let mut interpreter = Self::bare();
+        interpreter.load_prelude();
+        return interpreter;
+    }
+
+    /// Creates an interpreter with only the builtins, for embedders that
+    /// don't want the prelude.
+    pub fn bare() -> Self {
+        let mut environment_stack = EnvironmentStack::new();
+        define_builtins(&mut environment_stack);
+
         Self {
-            environment_stack: EnvironmentStack::new(),
+            environment_stack,
+            undefined_as_nil: false,
+            modules: HashMap::new(),
+            failed_modules: Vec::new(),
+            loading: Vec::new(),
+            current_file: None,
+            module_search_path: Vec::new(),
+            warnings: Vec::new(),
+            warned_locations: Vec::new(),
+            prelude: false,
+            check_contracts: true,
         }
     }
 
-    pub fn evaluate_file(&mut self, filepath: &str) -> Result<(), Error> {
-        let file_content = std::fs::read_to_string(filepath)
-            .map_err(|error| Error::new("IO error", ErrorType::IOError(error.kind())))?;
+    /// Makes undefined variables evaluate to `nil` instead of raising an
+    /// `UndefinedVariable` error, as older versions of risp did.
+    pub fn set_undefined_as_nil(&mut self, undefined_as_nil: bool) {
+        self.undefined_as_nil = undefined_as_nil;
+    }
 
-        let tokens = tokenize(&file_content);
-        let expressions = parse(&mut tokens.into_iter().peekable())?;
+    /// Turns the checking of the `:pre` and `:post` conditions of functions
+    /// on or off. They are checked by default.
+    pub fn set_check_contracts(&mut self, check_contracts: bool) {
+        self.check_contracts = check_contracts;
+    }
 
-        for expression in expressions.iter() {
-            self.evaluate(expression)?;
+    /// Raises a warning about the code at `span`, once however often it is
+    /// evaluated.
+    fn warn(&mut self, message: String, span: Span) {
+        let file = self.current_file.as_ref().map(|file| file.to_path_buf());
+        let location = (file.clone(), span);
+
+        if self.warned_locations.contains(&location) {
+            return;
         }
 
-        return Ok(());
+        self.warned_locations.push(location);
+        self.warnings.push(Warning {
+            message,
+            span,
+            file,
+        });
+    }
+
+    pub fn evaluate_file(&mut self, filepath: &str) -> Result<(), Error> {
+        let file_content = std::fs::read_to_string(filepath).map_err(Error::from)?;
+
+        let tokens = tokenize_with_spans(&file_content);
+        let expressions = parse(&mut tokens.into())?;
+
+        // Modules required by the file are looked up next to it.
+        let path = std::fs::canonicalize(filepath).map_err(Error::from)?;
+        self.loading.push(LoadingFile::new(&path));
+
+        let result = expressions
+            .iter()
+            .try_for_each(|expression| self.evaluate(expression).map(|_| ()));
+
+        self.loading.pop();
+
+        return result;
     }
 
     pub fn evaluate(&mut self, expression: &ASTNode) -> Result<Value, Error> {
@@ -44,13 +155,27 @@ impl Interpreter {
                     values.push(self.evaluate(expression)?);
                 }
 
-                Ok(Value::List(values))
+                Ok(Value::List(values.into()))
+            }
+
+            ASTNode::MapExpression(entries) => {
+                let mut map = Map::new();
+
+                for (key, value) in entries.iter() {
+                    let key = self.evaluate(key)?;
+                    let value = self.evaluate(value)?;
+                    map.insert(key, value);
+                }
+
+                Ok(Value::Map(map))
             }
 
             ASTNode::BooleanLiteral(value) => Ok(Value::Boolean(*value)),
 
-            ASTNode::CallExpression(ref name, ref arguments) => {
-                return self.evaluate_call_expression(name, arguments);
+            ASTNode::NilLiteral => Ok(Value::Nil),
+
+            ASTNode::CallExpression(ref callee, ref arguments, span) => {
+                return self.evaluate_call_expression(callee, arguments, *span);
             }
 
             ASTNode::IfExpression {
@@ -67,64 +192,124 @@ impl Interpreter {
 
             ASTNode::FunctionDeclaration {
                 identifier,
-                parameter_list,
-                body,
-            } => {
-                let function = Function::new(
-                    identifier.to_string(),
-                    parameter_list.clone(),
-                    body.deref().clone(),
-                );
+                docstring,
+                arities,
+            } => self.evaluate_function_declaration(identifier, docstring, arities, Map::new()),
+
+            ASTNode::MethodDeclaration {
+                identifier,
+                dispatch_value,
+                arities,
+            } => self.evaluate_method_declaration(identifier, dispatch_value, arities),
+
+            ASTNode::TypeExtension {
+                type_name,
+                protocol,
+                methods,
+            } => self.evaluate_type_extension(type_name, protocol, methods),
+
+            ASTNode::MetadataExpression {
+                metadata,
+                expression,
+            } => self.evaluate_metadata_expression(metadata, expression),
+
+            ASTNode::FunctionExpression { arities } => {
+                return Ok(Value::Function(self.declare_function("fn", arities)));
+            }
+
+            ASTNode::LetExpression { bindings, body } => {
+                let mut pushed_environments = 0;
+                let result =
+                    self.evaluate_let_expression(bindings, body, false, &mut pushed_environments);
+
+                for _ in 0..pushed_environments {
+                    self.environment_stack.pop_environment();
+                }
+
+                return result;
+            }
+
+            ASTNode::TryLetExpression { bindings, body } => {
+                let mut pushed_environments = 0;
+                let result =
+                    self.evaluate_let_expression(bindings, body, true, &mut pushed_environments);
 
-                self.environment_stack
-                    .set(identifier, Value::Function(function.clone()));
+                for _ in 0..pushed_environments {
+                    self.environment_stack.pop_environment();
+                }
 
-                return Ok(Value::Function(function));
+                return result;
             }
 
             ASTNode::StringLiteral(string) => {
                 return Ok(Value::String(string.to_string()));
             }
 
+            ASTNode::KeywordLiteral(name) => {
+                return Ok(Value::Keyword(Keyword::new(name)));
+            }
+
             ASTNode::Identifier(name) => {
                 if let Some(value) = self.environment_stack.get(name) {
                     return Ok(value.clone());
-                } else {
+                } else if self.undefined_as_nil {
                     return Ok(Value::Nil);
+                } else {
+                    return Err(self.undefined_variable(name));
                 }
             }
         }
     }
 
-    fn evaluate_call_expression(
+    fn undefined_variable(&self, name: &str) -> Error {
+        return Error::new(
+            "Undefined variable",
+            ErrorType::UndefinedVariable(name.to_string()),
+        )
+        .with_suggestion(self.environment_stack.suggest(name));
+    }
+
+    fn evaluate_let_expression(
         &mut self,
-        name: &str,
-        arguments: &Vec<ASTNode>,
+        bindings: &[(Pattern, ASTNode)],
+        body: &[ASTNode],
+        unwrap_results: bool,
+        pushed_environments: &mut usize,
     ) -> Result<Value, Error> {
-        match &name[..] {
-            "add" => {
-                let numbers = self.number_arguments(arguments)?;
-
-                if numbers.len() == 0 {
-                    return Err(Error::new("Too few arguments", ErrorType::TooFewArguments));
-                }
-
-                return Ok(Value::Number(numbers.iter().sum()));
-            }
+        // Every binding gets its own environment so later bindings can refer
+        // to the earlier ones.
+        for (pattern, value_node) in bindings.iter() {
+            let value = match self.evaluate(value_node)? {
+                Value::Ok(value) if unwrap_results => *value,
+                error @ Value::Err(_) if unwrap_results => return Ok(error),
+                value if unwrap_results => return Err(type_error(Type::Result, &value)),
+                value => value,
+            };
+
+            let mut variables = HashMap::new();
+            bind_pattern(pattern, value, &mut variables)?;
+
+            self.environment_stack.push_environment(variables);
+            *pushed_environments += 1;
+        }
 
-            "subtract" => {
-                let numbers = self.number_arguments(arguments)?;
+        let mut result = Value::Nil;
 
-                if numbers.len() == 0 {
-                    return Err(Error::new("Too few arguments", ErrorType::TooFewArguments));
-                }
+        for expression in body.iter() {
+            result = self.evaluate(expression)?;
+        }
 
-                return Ok(Value::Number(
-                    numbers.into_iter().reduce(|acc, a| acc - a).unwrap_or(0),
-                ));
-            }
+        return Ok(result);
+    }
 
-            "define" => match &arguments[..] {
+    fn evaluate_call_expression(
+        &mut self,
+        callee: &ASTNode,
+        arguments: &[ASTNode],
+        span: Span,
+    ) -> Result<Value, Error> {
+        let function = match callee {
+            ASTNode::Identifier(name) if name == "define" => match arguments {
                 [ASTNode::Identifier(name), value_node] => {
                     let value = self.evaluate(value_node)?;
                     self.environment_stack.set(name, value.clone());
@@ -139,26 +324,15 @@ impl Interpreter {
                 }
             },
 
-            "car" => match &arguments[..] {
-                [value_node] => {
+            ASTNode::Identifier(name) if name == "set!" => match arguments {
+                [ASTNode::Identifier(name), value_node] => {
                     let value = self.evaluate(value_node)?;
 
-                    match value {
-                        Value::List(values) => {
-                            let mut cloned_values: VecDeque<Value> = VecDeque::from(values);
-                            return Ok(cloned_values.pop_front().unwrap_or(Value::Nil));
-                        }
-
-                        _ => {
-                            return Err(Error::new(
-                                "Type error",
-                                ErrorType::TypeError {
-                                    expected_type: Type::List,
-                                    actual_type: value.value_type(),
-                                },
-                            ))
-                        }
+                    if !self.environment_stack.assign(name, value.clone()) {
+                        return Err(self.undefined_variable(name));
                     }
+
+                    return Ok(value);
                 }
 
                 _ => {
@@ -169,23 +343,10 @@ impl Interpreter {
                 }
             },
 
-            "is-empty" => match &arguments[..] {
-                [value_node] => {
-                    let value = self.evaluate(value_node)?;
-
-                    match value {
-                        Value::List(values) => Ok(Value::Boolean(values.is_empty())),
-
-                        _ => {
-                            return Err(Error::new(
-                                "Type error",
-                                ErrorType::TypeError {
-                                    expected_type: Type::List,
-                                    actual_type: value.value_type(),
-                                },
-                            ))
-                        }
-                    }
+            ASTNode::Identifier(name) if name == "lazy-seq" => match arguments {
+                [body] => {
+                    let thunk = self.body_thunk(name, body);
+                    return Ok(Value::LazySequence(LazySequence::new(thunk)));
                 }
 
                 _ => {
@@ -196,28 +357,10 @@ impl Interpreter {
                 }
             },
 
-            "append" => match &arguments[..] {
-                [list_node, value_node] => {
-                    let list = self.evaluate(list_node)?;
-                    let value = self.evaluate(value_node)?;
-
-                    match (list, &value) {
-                        (Value::List(values), value) => {
-                            let mut new_values = values.clone();
-                            new_values.push(value.clone());
-                            Ok(Value::List(new_values))
-                        }
-
-                        _ => {
-                            return Err(Error::new(
-                                "Type error",
-                                ErrorType::TypeError {
-                                    expected_type: Type::List,
-                                    actual_type: value.value_type(),
-                                },
-                            ))
-                        }
-                    }
+            ASTNode::Identifier(name) if name == "delay" => match arguments {
+                [body] => {
+                    let thunk = self.body_thunk(name, body);
+                    return Ok(Value::Promise(Promise::new(thunk)));
                 }
 
                 _ => {
@@ -228,179 +371,266 @@ impl Interpreter {
                 }
             },
 
-            "prepend" => match &arguments[..] {
-                [list_node, value_node] => {
-                    let list = self.evaluate(list_node)?;
-                    let value = self.evaluate(value_node)?;
+            ASTNode::Identifier(name) if name == "generator" => {
+                return Ok(self.evaluate_generator_expression(arguments));
+            }
 
-                    match (list, &value) {
-                        (Value::List(values), value) => {
-                            let mut new_values = VecDeque::from(values.clone());
-                            new_values.push_front(value.clone());
-                            Ok(Value::List(new_values.into_iter().collect()))
-                        }
-
-                        _ => {
-                            return Err(Error::new(
-                                "Type error",
-                                ErrorType::TypeError {
-                                    expected_type: Type::List,
-                                    actual_type: value.value_type(),
-                                },
-                            ))
-                        }
-                    }
-                }
+            ASTNode::Identifier(name) if name == "yield" || name == "yield-from" => {
+                return Err(generators::misplaced_yield(name));
+            }
 
-                _ => {
-                    return Err(Error::new(
-                        "Wrong number of arguments",
-                        ErrorType::ArgumentError,
-                    ));
-                }
-            },
+            ASTNode::Identifier(name) if name == "try" => {
+                return self.evaluate_try_expression(arguments);
+            }
 
-            "is-nil" => match &arguments[..] {
-                [value_node] => Ok(Value::Boolean(self.evaluate(value_node)?.is_nil())),
+            ASTNode::Identifier(name) if name == "defrecord" => {
+                return self.evaluate_defrecord_expression(arguments);
+            }
 
-                _ => {
-                    return Err(Error::new(
-                        "Wrong number of arguments",
-                        ErrorType::ArgumentError,
-                    ));
-                }
-            },
+            ASTNode::Identifier(name) if name == "deftype" => {
+                return self.evaluate_deftype_expression(arguments);
+            }
 
-            "cdr" => match &arguments[..] {
-                [value_node] => {
-                    let value = self.evaluate(value_node)?;
+            ASTNode::Identifier(name) if name == "match" => {
+                return self.evaluate_match_expression(arguments, span);
+            }
 
-                    match value {
-                        Value::List(values) => {
-                            let mut cloned_values: VecDeque<Value> = VecDeque::from(values);
-                            cloned_values.pop_front();
-
-                            return Ok(Value::List(cloned_values.into_iter().collect()));
-                        }
-
-                        _ => {
-                            return Err(Error::new(
-                                "Type error",
-                                ErrorType::TypeError {
-                                    expected_type: Type::List,
-                                    actual_type: value.value_type(),
-                                },
-                            ))
-                        }
-                    }
-                }
+            ASTNode::Identifier(name) if name == "bound?" => {
+                return self.evaluate_bound_expression(arguments);
+            }
 
-                _ => {
-                    return Err(Error::new(
-                        "Wrong number of arguments",
-                        ErrorType::ArgumentError,
-                    ));
-                }
-            },
+            ASTNode::Identifier(name) if name == "defmulti" => {
+                return self.evaluate_defmulti_expression(arguments);
+            }
 
-            "println" => match &arguments[..] {
-                nodes => {
-                    let mut values = Vec::new();
+            ASTNode::Identifier(name) if name == "defprotocol" => {
+                return self.evaluate_defprotocol_expression(arguments);
+            }
 
-                    for node in nodes.iter() {
-                        values.push(self.evaluate(node)?);
-                    }
+            ASTNode::Identifier(name) if name == "ns" => {
+                return self.evaluate_ns_expression(arguments);
+            }
 
-                    println!(
-                        "{}",
-                        values
-                            .iter()
-                            .map(|a| a.to_display_string())
-                            .collect::<Vec<String>>()
-                            .join(" ")
-                    );
+            ASTNode::Identifier(name) if name == "require" || name == "import" => {
+                return self.evaluate_require_expression(arguments, name == "import");
+            }
 
-                    return Ok(Value::List(values));
+            ASTNode::Identifier(name) => match self.environment_stack.get(name) {
+                Some(function) => function,
+                None => {
+                    return Err(Error::new(
+                        "Undefined",
+                        ErrorType::UndefinedFunction(name.to_string()),
+                    )
+                    .with_suggestion(self.environment_stack.suggest(name)))
                 }
             },
 
-            name => {
-                let value = {
-                    let value = self.environment_stack.get(name);
-                    value.clone().ok_or(Error::new(
-                        "Undefined",
-                        ErrorType::UndefinedFunction(name.to_string()),
-                    ))?
-                };
+            callee => self.evaluate(callee)?,
+        };
 
-                if let Value::Function(function) = value {
-                    if function.parameter_list().len() != arguments.len() {
-                        return Err(Error::new("Too few arguments", ErrorType::TooFewArguments));
-                    }
+        self.warn_if_deprecated(&function, span);
 
-                    // Push the argument values onto the stack
-                    let mut values = Vec::new();
+        let (values, keyword_values) = self.evaluate_arguments(&function, arguments)?;
 
-                    for argument in arguments.iter() {
-                        values.push(self.evaluate(argument)?);
-                    }
+        return self
+            .call_value_with_keyword_arguments(&function, values, keyword_values)
+            .map_err(|error| error.at_call_site(span));
+    }
 
-                    let mut arguments: HashMap<String, Value> = HashMap::new();
+    /// Binds the function a `defn` declares, with its docstring as `:doc` in
+    /// its metadata. This is kept out of `evaluate` so the temporaries don't
+    /// enlarge its stack frame, which every nested call pays for.
+    fn evaluate_function_declaration(
+        &mut self,
+        identifier: &str,
+        docstring: &Option<Rc<str>>,
+        arities: &[FunctionArity],
+        mut metadata: Map,
+    ) -> Result<Value, Error> {
+        if let Some(docstring) = docstring {
+            metadata.insert(
+                Value::Keyword(Keyword::new("doc")),
+                Value::String(docstring.to_string()),
+            );
+        }
 
-                    for (index, key) in function.parameter_list().iter().enumerate() {
-                        let value = &values[index];
+        let function = self
+            .declare_function(identifier, arities)
+            .with_meta((!metadata.is_empty()).then(|| Rc::new(metadata)));
 
-                        arguments.insert(key.to_string(), value.clone());
-                    }
+        self.environment_stack
+            .set(identifier, Value::Function(function.clone()));
 
-                    self.environment_stack.push_environment(arguments);
+        return Ok(Value::Function(function));
+    }
 
-                    let result = self.evaluate(&function.body().clone());
+    /// Creates a function closing over the current environment.
+    fn declare_function(&self, identifier: &str, arities: &[FunctionArity]) -> Function {
+        return Function::new(
+            identifier.to_string(),
+            arities.to_vec(),
+            self.environment_stack.closure_scope(),
+            self.current_file.clone(),
+        );
+    }
 
-                    // Pop the call stack
-                    self.environment_stack.pop_environment();
+    /// Captures `body` and the current environment in a thunk that evaluates
+    /// the body later, as used by `lazy-seq` and `delay`.
+    fn body_thunk(&self, name: &str, body: &ASTNode) -> Thunk {
+        let function = self.declare_function(
+            name,
+            &[FunctionArity {
+                parameter_list: ParameterList::default(),
+                return_type: None,
+                conditions: Conditions::default(),
+                body: body.clone(),
+            }],
+        );
+
+        return Box::new(move |interpreter| {
+            interpreter.call_function(&function, Vec::new(), Vec::new())
+        });
+    }
 
-                    return result;
-                } else {
-                    Err(Error::new(
-                        "Not a function",
-                        ErrorType::NotAFunction(name.to_string()),
-                    ))
+    /// Evaluates the arguments of a call. For functions declaring `&key`
+    /// parameters, keyword arguments written as `:name value` pairs are
+    /// separated from the positional ones, while other functions get
+    /// keywords as values.
+    fn evaluate_arguments(
+        &mut self,
+        function: &Value,
+        arguments: &[ASTNode],
+    ) -> Result<(Vec<Value>, KeywordArguments), Error> {
+        let mut values = Vec::new();
+        let mut keyword_values = Vec::new();
+        let mut nodes = arguments.iter();
+
+        let takes_keyword_arguments = match function {
+            Value::Function(function) => function
+                .arities()
+                .iter()
+                .any(|arity| !arity.parameter_list.keywords.is_empty()),
+            _ => false,
+        };
+
+        while let Some(node) = nodes.next() {
+            match node {
+                ASTNode::KeywordLiteral(keyword) if takes_keyword_arguments => {
+                    let value_node = nodes.next().ok_or(Error::new(
+                        "Missing value for keyword argument",
+                        ErrorType::ArgumentError,
+                    ))?;
+
+                    keyword_values.push((keyword.to_string(), self.evaluate(value_node)?));
                 }
+
+                node => values.push(self.evaluate(node)?),
             }
         }
+
+        return Ok((values, keyword_values));
     }
 
-    fn number_arguments(&mut self, arguments: &Vec<ASTNode>) -> Result<Vec<i64>, Error> {
-        let values = arguments.iter().map(|argument| self.evaluate(argument));
-        let mut numbers: Vec<i64> = vec![];
+    /// Calls a function value, either user defined or builtin, with already
+    /// evaluated arguments.
+    pub(crate) fn call_value(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Error> {
+        return self.call_value_with_keyword_arguments(function, arguments, Vec::new());
+    }
 
-        for value in values {
-            match value {
-                Ok(Value::Number(number)) => numbers.push(number),
+    fn call_value_with_keyword_arguments(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+        keyword_arguments: KeywordArguments,
+    ) -> Result<Value, Error> {
+        match function {
+            Value::Function(function) => self.call_function(function, arguments, keyword_arguments),
 
-                Ok(value) => {
+            Value::Builtin(builtin) => {
+                if let Some((keyword, _)) = keyword_arguments.into_iter().next() {
                     return Err(Error::new(
-                        "add requires all arguments to be Numbers",
-                        ErrorType::TypeError {
-                            expected_type: crate::Type::Number,
-                            actual_type: value.value_type(),
-                        },
+                        "Unexpected keyword argument",
+                        ErrorType::UnexpectedKeywordArgument(keyword),
                     ));
                 }
 
-                Err(error) => return Err(error),
+                builtin
+                    .call(self, arguments)
+                    .map_err(|error| error.with_frame(builtin.name(), String::new()))
             }
-        }
 
-        return Ok(numbers);
+            Value::Keyword(keyword) => call_keyword(keyword, &arguments),
+
+            Value::MultiMethod(multi_method) => {
+                self.call_multi_method(multi_method, arguments, keyword_arguments)
+            }
+
+            _ => Err(Error::new(
+                "Not a function",
+                ErrorType::NotAFunction(function.value_type()),
+            )),
+        }
     }
 }
 
+/// `(:name map)` looks the keyword up in a map, or a record field of that
+/// name, returning nil or the default given after the map when it's missing.
+fn call_keyword(keyword: &Keyword, arguments: &[Value]) -> Result<Value, Error> {
+    let (collection, default) = match arguments {
+        [collection] => (collection, &Value::Nil),
+        [collection, default] => (collection, default),
+        _ => {
+            return Err(
+                Error::new("Wrong number of arguments", ErrorType::ArgumentError)
+                    .with_frame(&format!(":{}", keyword.name()), String::new()),
+            )
+        }
+    };
+
+    let value = match collection {
+        Value::Map(map) => map.get(&Value::Keyword(keyword.clone())),
+        Value::Record(record) => record.get(keyword.name()),
+        _ => None,
+    };
+
+    return Ok(value.unwrap_or(default).clone());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Evaluates every expression of `source` and returns the value of the
+    /// last one. The tests of the other interpreter modules use it too.
+    pub(crate) fn evaluate_source(
+        interpreter: &mut Interpreter,
+        source: &str,
+    ) -> Result<Value, Error> {
+        let tokens = tokenize_with_spans(source);
+        let expressions = parse(&mut tokens.into())?;
+        let mut result = Value::Nil;
+
+        for expression in expressions.iter() {
+            result = interpreter.evaluate(expression)?;
+        }
+
+        return Ok(result);
+    }
+
+    /// Evaluates `source` in a new interpreter and displays its value, with
+    /// lazy sequences realized.
+    pub(crate) fn display(source: &str) -> String {
+        let mut interpreter = Interpreter::new();
+        let value = evaluate_source(&mut interpreter, source).unwrap();
+
+        return interpreter.realize(value).unwrap().to_display_string();
+    }
+
     #[test]
     fn test_reading_variables() {
         let mut interpreter = Interpreter::new();
@@ -412,4 +642,616 @@ mod tests {
 
         assert_eq!(result, Ok(Value::Number(3)));
     }
+
+    #[test]
+    fn test_destructuring_function_parameters() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn f [[a b] & rest] (list a b rest))
+             (f (list 1 2) 3 4)",
+        );
+
+        assert_eq!(
+            result,
+            Ok(Value::List(
+                vec![
+                    Value::Number(1),
+                    Value::Number(2),
+                    Value::List(vec![Value::Number(3), Value::Number(4)].into()),
+                ]
+                .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_destructuring_let_bindings() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            r#"(let [{:keys [x y]} {"x" 1 "y" 2}
+                     [a & more] (list x y)]
+                 (list a more))"#,
+        );
+
+        assert_eq!(
+            result,
+            Ok(Value::List(
+                vec![Value::Number(1), Value::List(vec![Value::Number(2)].into()),].into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_destructuring_shape_mismatch() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn f [[a b]] a)
+             (f (list 1 2 3))",
+        );
+
+        assert_eq!(
+            result,
+            Err(Error::new(
+                "Wrong number of elements to destructure",
+                ErrorType::DestructuringError {
+                    expected_length: 2,
+                    actual_length: 3,
+                },
+            ))
+        );
+    }
+
+    #[test]
+    fn test_optional_and_rest_parameters() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn f [a &optional (b (add a 1)) & more] (list a b more))
+             (list (f 1) (f 1 5) (f 1 5 6 7))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("((1 2 ()) (1 5 ()) (1 5 (6 7)))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_keyword_parameters() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn scale [a &key (by 2) offset] (list (add a a) by offset))
+             (list (scale 1) (scale 1 :offset 3 :by 10))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("((2 2 nil) (2 10 3))".to_string())
+        );
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(scale 1 :factor 3)"),
+            Err(Error::new(
+                "Unexpected keyword argument",
+                ErrorType::UnexpectedKeywordArgument("factor".to_string()),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_keywords() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define person {:name \"Ada\" :born 1815})
+             (defn tag [a] a)
+             (list (:name person)
+                   (:age person)
+                   (:age person 36)
+                   (get person :born)
+                   (tag :red)
+                   (is-equal :red :red)
+                   (is-equal :red \"red\")
+                   (match :green :red 1 :green 2))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(Ada nil 36 1815 :red true false 2)".to_string())
+        );
+
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(defrecord Point [x y])
+                 (list (:x (Point 1 2)) (:z (Point 1 2)) (:z (Point 1 2) 0))"
+            )
+            .map(|value| value.to_display_string()),
+            Ok("(1 nil 0)".to_string())
+        );
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(list person (type-of :red))")
+                .map(|value| value.to_display_string()),
+            Ok("({:name Ada :born 1815} Keyword)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_multi_arity_functions() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn greet ([] (greet \"world\")) ([name] (list \"hello\" name)))
+             (greet)",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(hello world)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_wrong_number_of_arguments() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(&mut interpreter, "(defn f [a b] a)").unwrap();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(f 1)"),
+            Err(Error::new(
+                "Too few arguments",
+                ErrorType::TooFewArguments {
+                    expected: 2,
+                    given: 1,
+                },
+            ))
+        );
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(f 1 2 3)"),
+            Err(Error::new(
+                "Too many arguments",
+                ErrorType::TooManyArguments {
+                    expected: 2,
+                    given: 3,
+                },
+            ))
+        );
+    }
+
+    #[test]
+    fn test_no_arity_for_the_number_of_arguments() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(
+            &mut interpreter,
+            "(defn f ([a] a) ([a b c] a) ([a b c d e &optional f & more] a))",
+        )
+        .unwrap();
+
+        let error = evaluate_source(&mut interpreter, "(f 1 2)").unwrap_err();
+
+        assert_eq!(
+            error.error_type(),
+            &ErrorType::NoMatchingArity {
+                accepted: vec![(1, Some(1)), (3, Some(3)), (5, None)],
+                given: 2,
+            }
+        );
+        assert_eq!(
+            error.message(),
+            "argument error: expected 1, 3 or 5 or more arguments, got 2"
+        );
+    }
+
+    #[test]
+    fn test_builtins_as_values() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define plus add)
+             (defn twice [f a] (f a a))
+             (list (plus 1 2) (twice add 3) (apply plus 1 (list 2 3)))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(3 6 6)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_errors() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(multiply 9223372036854775807 2)"),
+            Err(Error::new("Integer overflow", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(divide (subtract 0 9223372036854775807 1) -1)"
+            ),
+            Err(Error::new("Integer overflow", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(add 9223372036854775807 1)"),
+            Err(Error::new("Integer overflow", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(subtract 0 9223372036854775807 2)"),
+            Err(Error::new("Integer overflow", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(remainder (subtract 0 9223372036854775807 1) -1)"
+            ),
+            Err(Error::new("Integer overflow", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(remainder 1 0)"),
+            Err(Error::new("Division by zero", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(divide 1 0)"),
+            Err(Error::new("Division by zero", ErrorType::ArgumentError))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(list (multiply 2 3 4) (divide 7 2))"),
+            Ok(Value::List(
+                vec![Value::Number(24), Value::Number(3)].into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_shadowing_builtins() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn car [a] \"shadowed\")
+             (car (list 1))",
+        );
+
+        assert_eq!(result, Ok(Value::String("shadowed".to_string())));
+    }
+
+    #[test]
+    fn test_applying_user_functions() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn f [a & more] (list a more))
+             (apply f 1 (list 2 3))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(1 (2 3))".to_string())
+        );
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(list (apply add (take 3 (range))) (apply f 1 nil))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(3 (1 ()))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_calling_returned_closures() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn make-adder [n] (fn [a] (add a n)))
+             ((make-adder 1) 2)",
+        );
+
+        assert_eq!(result, Ok(Value::Number(3)));
+    }
+
+    #[test]
+    fn test_calling_functions_stored_in_lists() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define operations (list add subtract))
+             ((car (cdr operations)) 5 3)",
+        );
+
+        assert_eq!(result, Ok(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_functions_do_not_see_caller_bindings() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(defn inner [] secret)
+             (let [secret 1] (inner))",
+        );
+
+        assert_eq!(
+            result,
+            Err(Error::new(
+                "Undefined variable",
+                ErrorType::UndefinedVariable("secret".to_string()),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_calling_non_functions() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "((list 1) 2)"),
+            Err(Error::new(
+                "Not a function",
+                ErrorType::NotAFunction(Type::List),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_set_updates_outer_bindings() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define total 0)
+             (defn add-to-total [a] (set! total (add total a)))
+             (add-to-total 2)
+             (add-to-total 3)
+             total",
+        );
+
+        assert_eq!(result, Ok(Value::Number(5)));
+    }
+
+    #[test]
+    fn test_set_updates_captured_bindings() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define counter (let [count 0] (fn [] (set! count (add count 1)))))
+             (counter)
+             (counter)",
+        );
+
+        assert_eq!(result, Ok(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_define_binds_in_the_current_scope() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define a 1)
+             (defn shadow [] (define a 2))
+             (shadow)
+             a",
+        );
+
+        assert_eq!(result, Ok(Value::Number(1)));
+    }
+
+    #[test]
+    fn test_set_requires_an_existing_binding() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(set! missing 1)"),
+            Err(Error::new(
+                "Undefined variable",
+                ErrorType::UndefinedVariable("missing".to_string()),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_undefined_variables_are_errors() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define my-list (list 1 2))
+             (println my-lsit)",
+        );
+
+        assert_eq!(
+            result,
+            Err(Error::new(
+                "Undefined variable",
+                ErrorType::UndefinedVariable("my-lsit".to_string()),
+            )
+            .with_suggestion(Some("my-list".to_string())))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(prinln 1)")
+                .unwrap_err()
+                .suggestion(),
+            Some("println")
+        );
+    }
+
+    #[test]
+    fn test_contracts() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(
+            &mut interpreter,
+            "(defn halve [a] {:pre [(is-greater a 0) (is-equal (remainder a 2) 0)]
+                              :post [(is-less % a)]}
+               (divide a 2))
+             (defn broken [a] {:post [(is-greater % 0)]} (subtract a 10))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(halve 8)"),
+            Ok(Value::Number(4))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(halve 3)"),
+            Err(Error::new(
+                "Contract violation",
+                ErrorType::ContractViolation {
+                    function: "halve".to_string(),
+                    condition: "(is-equal (remainder a 2) 0)".to_string(),
+                    postcondition: false,
+                }
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(broken 5)")
+                .unwrap_err()
+                .to_string(),
+            "contract violation: postcondition (is-greater % 0) of broken failed in call to broken"
+        );
+
+        interpreter.set_check_contracts(false);
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(list (halve 3) (broken 5))")
+                .map(|value| value.to_display_string()),
+            Ok("(1 -5)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_undefined_variables_as_nil() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_undefined_as_nil(true);
+
+        assert_eq!(evaluate_source(&mut interpreter, "missing"), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn test_errors_carry_a_backtrace() {
+        let mut interpreter = Interpreter::new();
+
+        let error = evaluate_source(
+            &mut interpreter,
+            "(defn inner [a] (add a \"b\"))
+             (defn outer [x & rest]
+               (map inner (list x)))
+             (outer 1 2)",
+        )
+        .unwrap_err();
+
+        let frames: Vec<(&str, Option<Span>, &str)> = error
+            .backtrace()
+            .iter()
+            .map(|frame| {
+                (
+                    frame.function.as_str(),
+                    frame.span,
+                    frame.arguments.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            frames,
+            vec![
+                (
+                    "add",
+                    Some(Span {
+                        line: 1,
+                        column: 17
+                    }),
+                    ""
+                ),
+                ("inner", None, "a=1"),
+                (
+                    "map",
+                    Some(Span {
+                        line: 3,
+                        column: 16
+                    }),
+                    ""
+                ),
+                (
+                    "outer",
+                    Some(Span {
+                        line: 4,
+                        column: 14
+                    }),
+                    "x=1, rest=(2)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let mut interpreter = Interpreter::new();
+        let mut message = |source: &str| {
+            evaluate_source(&mut interpreter, source)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            message("(car 1)"),
+            "type error: expected List, got Number in call to car"
+        );
+        assert_eq!(
+            message("(define my-list 1) (add my-lsit 1)"),
+            "undefined variable: my-lsit (did you mean my-list?)"
+        );
+        assert_eq!(
+            message("(defn f [a] a) (f)"),
+            "argument error: expected at least 1 argument, got 0 in call to f"
+        );
+        assert_eq!(message("(fn [a"), "syntax error: unexpected end of input");
+        assert_eq!(
+            message("(defn f a)"),
+            "syntax error: unexpected 'a', expected a parameter list"
+        );
+    }
+
+    #[test]
+    fn test_io_errors_have_a_source() {
+        use std::error::Error as _;
+
+        let mut interpreter = Interpreter::new();
+        let error =
+            evaluate_source(&mut interpreter, "(read-file \"/does/not/exist\")").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "io error: entity not found in call to read-file"
+        );
+        assert_eq!(
+            error
+                .source()
+                .and_then(|source| source.downcast_ref::<std::io::Error>())
+                .map(|source| source.kind()),
+            Some(std::io::ErrorKind::NotFound)
+        );
+    }
 }
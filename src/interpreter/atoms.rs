@@ -0,0 +1,200 @@
+use super::{
+    builtins::{argument_error, type_error},
+    caching::force,
+    Interpreter,
+};
+use crate::{
+    value::{Atom, BuiltinFunction},
+    Error, Type, Value,
+};
+
+pub const ATOM_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("atom", atom),
+    ("deref", deref),
+    ("reset!", reset),
+    ("swap!", swap),
+    ("compare-and-set!", compare_and_set),
+    ("add-watch", add_watch),
+    ("remove-watch", remove_watch),
+];
+
+impl Interpreter {
+    /// Stores `value` in `atom` and notifies its watches if the value changed.
+    fn update_atom(&mut self, atom: &Atom, value: Value) -> Result<(), Error> {
+        let old_value = atom.replace(value.clone());
+
+        if old_value == value {
+            return Ok(());
+        }
+
+        for (key, function) in atom.watches() {
+            self.call_value(
+                &function,
+                vec![
+                    key,
+                    Value::Atom(atom.clone()),
+                    old_value.clone(),
+                    value.clone(),
+                ],
+            )?;
+        }
+
+        return Ok(());
+    }
+}
+
+fn atom(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [value]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+
+    return Ok(Value::Atom(Atom::new(value)));
+}
+
+/// `(deref atom)`, also written `@atom`. Forces promises as well.
+fn deref(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::Atom(atom)] => Ok(atom.value()),
+        [Value::Promise(_)] => force(interpreter, arguments),
+        [value] => Err(type_error(Type::Atom, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+/// `(reset! atom value)` replaces the value of the atom.
+fn reset(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([Value::Atom(atom), value]) => {
+            interpreter.update_atom(&atom, value.clone())?;
+            Ok(value)
+        }
+        Ok([value, _]) => Err(type_error(Type::Atom, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(swap! atom f args...)` replaces the value of the atom with
+/// `(f value args...)` and returns the new value.
+fn swap(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let mut arguments = arguments.into_iter();
+
+    let (atom, function) = match (arguments.next(), arguments.next()) {
+        (Some(Value::Atom(atom)), Some(function)) => (atom, function),
+        (Some(value), Some(_)) => return Err(type_error(Type::Atom, &value)),
+        _ => return Err(argument_error()),
+    };
+
+    let mut function_arguments = vec![atom.value()];
+    function_arguments.extend(arguments);
+
+    let value = interpreter.call_value(&function, function_arguments)?;
+    interpreter.update_atom(&atom, value.clone())?;
+
+    return Ok(value);
+}
+
+/// `(compare-and-set! atom old new)` only sets the atom to `new` if its
+/// current value equals `old`, and returns whether it did.
+fn compare_and_set(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 3]>::try_from(arguments) {
+        Ok([Value::Atom(atom), old_value, new_value]) => {
+            if atom.value() != old_value {
+                return Ok(Value::Boolean(false));
+            }
+
+            interpreter.update_atom(&atom, new_value)?;
+            Ok(Value::Boolean(true))
+        }
+        Ok([value, _, _]) => Err(type_error(Type::Atom, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(add-watch atom key f)` calls `(f key atom old new)` whenever the value
+/// of the atom changes. Adding a watch with an existing key replaces it.
+fn add_watch(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 3]>::try_from(arguments) {
+        Ok([Value::Atom(atom), key, function]) => {
+            atom.add_watch(key, function);
+            Ok(Value::Atom(atom))
+        }
+        Ok([value, _, _]) => Err(type_error(Type::Atom, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+fn remove_watch(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([Value::Atom(atom), key]) => {
+            atom.remove_watch(&key);
+            Ok(Value::Atom(atom))
+        }
+        Ok([value, _]) => Err(type_error(Type::Atom, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_updating_atoms() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(&mut interpreter, "(define counter (atom 0))").unwrap();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(swap! counter add 5)"),
+            Ok(Value::Number(5))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(reset! counter 10)"),
+            Ok(Value::Number(10))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "@counter"),
+            Ok(Value::Number(10))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(compare-and-set! counter 1 2)"),
+            Ok(Value::Boolean(false))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(compare-and-set! counter 10 2)"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(deref counter)"),
+            Ok(Value::Number(2))
+        );
+    }
+
+    #[test]
+    fn test_atoms_are_shared() {
+        let result = display(
+            "(define counter (atom 0))
+             (defn increment [a] (swap! a add 1))
+             (map (fn [_] (increment counter)) (range 3))
+             @counter",
+        );
+
+        assert_eq!(result, "3");
+    }
+
+    #[test]
+    fn test_watches() {
+        let result = display(
+            "(define log (atom (list)))
+             (define counter (atom 0))
+             (add-watch counter \"logger\"
+               (fn [key a old new] (swap! log append (list key old new))))
+             (swap! counter add 1)
+             (reset! counter 1)
+             (reset! counter 5)
+             (remove-watch counter \"logger\")
+             (reset! counter 6)
+             @log",
+        );
+
+        assert_eq!(result, "((logger 0 1) (logger 1 5))");
+    }
+}
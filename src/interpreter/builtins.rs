@@ -0,0 +1,367 @@
+use super::{
+    atoms::ATOM_BUILTINS,
+    caching::CACHING_BUILTINS,
+    docs::DOC_BUILTINS,
+    environment::EnvironmentStack,
+    exceptions::EXCEPTION_BUILTINS,
+    lazy::LAZY_BUILTINS,
+    metadata::METADATA_BUILTINS,
+    multimethods::MULTIMETHOD_BUILTINS,
+    results::RESULT_BUILTINS,
+    sequences::{sequence_values, SEQUENCE_BUILTINS},
+    Interpreter,
+};
+use crate::{
+    value::{Builtin, BuiltinFunction, LazySequence},
+    Error, ErrorType, Type, Value,
+};
+
+const BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("add", add),
+    ("subtract", subtract),
+    ("multiply", multiply),
+    ("divide", divide),
+    ("remainder", remainder),
+    ("car", car),
+    ("cdr", cdr),
+    ("is-empty", is_empty),
+    ("append", append),
+    ("prepend", prepend),
+    ("is-nil", is_nil),
+    ("is-equal", is_equal),
+    ("is-less", is_less),
+    ("is-greater", is_greater),
+    ("get", get),
+    ("println", println),
+    ("str", str),
+    ("read-file", read_file),
+    ("parse-number", parse_number),
+    ("apply", apply),
+];
+
+pub const BUILTIN_TABLES: &[&[(&str, BuiltinFunction)]] = &[
+    BUILTINS,
+    SEQUENCE_BUILTINS,
+    LAZY_BUILTINS,
+    CACHING_BUILTINS,
+    ATOM_BUILTINS,
+    EXCEPTION_BUILTINS,
+    RESULT_BUILTINS,
+    MULTIMETHOD_BUILTINS,
+    DOC_BUILTINS,
+    METADATA_BUILTINS,
+];
+
+/// Binds every builtin function in the global environment.
+pub fn define_builtins(environment_stack: &mut EnvironmentStack) {
+    for (name, function) in BUILTIN_TABLES.iter().flat_map(|table| table.iter()) {
+        environment_stack.define_builtin(name, Value::Builtin(Builtin::new(name, *function)));
+    }
+}
+
+pub fn argument_error() -> Error {
+    Error::new("Wrong number of arguments", ErrorType::ArgumentError)
+}
+
+pub fn type_error(expected_type: Type, value: &Value) -> Error {
+    Error::new(
+        "Type error",
+        ErrorType::TypeError {
+            expected_type,
+            actual_type: value.value_type(),
+        },
+    )
+}
+
+fn number_arguments(arguments: &[Value]) -> Result<Vec<i64>, Error> {
+    let mut numbers: Vec<i64> = vec![];
+
+    for value in arguments.iter() {
+        match value {
+            Value::Number(number) => numbers.push(*number),
+            value => return Err(type_error(Type::Number, value)),
+        }
+    }
+
+    if numbers.is_empty() {
+        return Err(Error::new(
+            "Too few arguments",
+            ErrorType::TooFewArguments {
+                expected: 1,
+                given: 0,
+            },
+        ));
+    }
+
+    return Ok(numbers);
+}
+
+fn add(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let numbers = number_arguments(&arguments)?;
+
+    numbers
+        .iter()
+        .try_fold(0i64, |acc, number| acc.checked_add(*number))
+        .map(Value::Number)
+        .ok_or_else(overflow)
+}
+
+fn subtract(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let numbers = number_arguments(&arguments)?;
+
+    let [minuend, subtrahends @ ..] = &numbers[..] else {
+        return Ok(Value::Number(0));
+    };
+
+    subtrahends
+        .iter()
+        .try_fold(*minuend, |acc, number| acc.checked_sub(*number))
+        .map(Value::Number)
+        .ok_or_else(overflow)
+}
+
+fn multiply(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let numbers = number_arguments(&arguments)?;
+
+    numbers
+        .iter()
+        .try_fold(1i64, |acc, number| acc.checked_mul(*number))
+        .map(Value::Number)
+        .ok_or_else(overflow)
+}
+
+/// Divides the first argument by the others in turn, rounding towards zero.
+fn divide(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let numbers = number_arguments(&arguments)?;
+
+    let [dividend, divisors @ ..] = &numbers[..] else {
+        return Err(argument_error());
+    };
+
+    divisors
+        .iter()
+        .try_fold(*dividend, |acc, divisor| match divisor {
+            0 => Err(division_by_zero()),
+            divisor => acc.checked_div(*divisor).ok_or_else(overflow),
+        })
+        .map(Value::Number)
+}
+
+/// `(remainder a b)` has the sign of `a`, e.g. `(remainder -7 2)` is `-1`.
+fn remainder(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &number_arguments(&arguments)?[..] {
+        [_, 0] => Err(division_by_zero()),
+        [a, b] => a.checked_rem(*b).map(Value::Number).ok_or_else(overflow),
+        _ => Err(argument_error()),
+    }
+}
+
+fn division_by_zero() -> Error {
+    Error::new("Division by zero", ErrorType::ArgumentError)
+}
+
+fn overflow() -> Error {
+    Error::new("Integer overflow", ErrorType::ArgumentError)
+}
+
+fn car(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::List(values)] => Ok(values.first().cloned().unwrap_or(Value::Nil)),
+        [Value::LazySequence(sequence)] => Ok(interpreter
+            .sequence_step(Value::LazySequence(sequence.clone()))?
+            .map_or(Value::Nil, |(first, _)| first)),
+        [value] => Err(type_error(Type::List, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn cdr(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::List(values)] => Ok(Value::List(values.iter().skip(1).cloned().collect())),
+        [Value::LazySequence(sequence)] => Ok(interpreter
+            .sequence_step(Value::LazySequence(sequence.clone()))?
+            .map_or(Value::List(Vec::new().into()), |(_, rest)| rest)),
+        [value] => Err(type_error(Type::List, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn is_empty(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::List(values)] => Ok(Value::Boolean(values.is_empty())),
+        [Value::LazySequence(sequence)] => Ok(Value::Boolean(
+            interpreter
+                .sequence_step(Value::LazySequence(sequence.clone()))?
+                .is_none(),
+        )),
+        [value] => Err(type_error(Type::List, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn append(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::List(values), value] => {
+            let mut new_values = values.clone();
+            new_values.push(value.clone());
+            Ok(Value::List(new_values))
+        }
+        [list, _] => Err(type_error(Type::List, list)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn prepend(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::List(values), value] => {
+            let mut new_values = Vec::with_capacity(values.len() + 1);
+            new_values.push(value.clone());
+            new_values.extend(values.iter().cloned());
+            Ok(Value::List(new_values.into()))
+        }
+        [sequence @ Value::LazySequence(_), value] => Ok(Value::LazySequence(LazySequence::cons(
+            value.clone(),
+            sequence.clone(),
+        ))),
+        [list, _] => Err(type_error(Type::List, list)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn is_nil(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [value] => Ok(Value::Boolean(value.is_nil())),
+        _ => Err(argument_error()),
+    }
+}
+
+fn is_equal(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [first, rest @ ..] if !rest.is_empty() => {
+            Ok(Value::Boolean(rest.iter().all(|value| value == first)))
+        }
+        _ => Err(argument_error()),
+    }
+}
+
+/// Checks that every pair of adjacent arguments satisfies `compare`. Numbers
+/// and strings can be compared, but not with each other.
+fn compare_adjacent(
+    arguments: &[Value],
+    compare: fn(std::cmp::Ordering) -> bool,
+) -> Result<Value, Error> {
+    if arguments.len() < 2 {
+        return Err(argument_error());
+    }
+
+    for pair in arguments.windows(2) {
+        let ordering = match (&pair[0], &pair[1]) {
+            (Value::Number(a), Value::Number(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Number(_), b) => return Err(type_error(Type::Number, b)),
+            (Value::String(_), b) => return Err(type_error(Type::String, b)),
+            (a, _) => return Err(type_error(Type::Number, a)),
+        };
+
+        if !compare(ordering) {
+            return Ok(Value::Boolean(false));
+        }
+    }
+
+    return Ok(Value::Boolean(true));
+}
+
+fn is_less(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    compare_adjacent(&arguments, std::cmp::Ordering::is_lt)
+}
+
+fn is_greater(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    compare_adjacent(&arguments, std::cmp::Ordering::is_gt)
+}
+
+fn get(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::Map(map), key] => Ok(map.get(key).cloned().unwrap_or(Value::Nil)),
+        [map, _] => Err(type_error(Type::Map, map)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn println(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let arguments = arguments
+        .into_iter()
+        .map(|argument| interpreter.realize(argument))
+        .collect::<Result<Vec<Value>, Error>>()?;
+
+    println!(
+        "{}",
+        arguments
+            .iter()
+            .map(|a| a.to_display_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    );
+
+    return Ok(Value::List(arguments.into()));
+}
+
+/// `(str a b ...)` concatenates the display strings of its arguments, leaving
+/// out nil.
+fn str(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let mut string = String::new();
+
+    for argument in arguments.into_iter() {
+        match interpreter.realize(argument)? {
+            Value::Nil => (),
+            Value::String(part) => string.push_str(&part),
+            value => string.push_str(&value.to_display_string()),
+        }
+    }
+
+    return Ok(Value::String(string));
+}
+
+/// `(read-file path)` returns the contents of a file as a string.
+pub fn read_file(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::String(path)] => std::fs::read_to_string(path)
+            .map(Value::String)
+            .map_err(Error::from),
+        [value] => Err(type_error(Type::String, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+/// `(parse-number string)` parses a decimal integer, e.g. `"-12"`.
+pub fn parse_number(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::String(string)] => string.trim().parse().map(Value::Number).map_err(|_| {
+            Error::new(
+                "Invalid number",
+                ErrorType::InvalidNumber(string.to_string()),
+            )
+        }),
+        [value] => Err(type_error(Type::String, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+/// `(apply f a b (list c d))` calls `f` with the arguments `a b c d`. The
+/// last argument can be any sequence, including a lazy one or `nil`.
+fn apply(interpreter: &mut Interpreter, mut arguments: Vec<Value>) -> Result<Value, Error> {
+    if arguments.len() < 2 {
+        return Err(argument_error());
+    }
+
+    let function = arguments.remove(0);
+
+    match arguments.pop() {
+        Some(sequence) => {
+            let spread_arguments = sequence_values(interpreter, sequence)?;
+            arguments.extend(spread_arguments);
+            interpreter.call_value(&function, arguments)
+        }
+
+        None => Err(argument_error()),
+    }
+}
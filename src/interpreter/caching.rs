@@ -0,0 +1,197 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::{
+    builtins::{argument_error, type_error},
+    Interpreter,
+};
+use crate::{
+    value::{Builtin, BuiltinFunction, LazyState, PromiseState},
+    Error, ErrorType, Type, Value,
+};
+
+pub const CACHING_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("force", force),
+    ("is-realized", is_realized),
+    ("memoize", memoize),
+];
+
+/// `(force promise)` evaluates the body of a `delay` the first time and
+/// returns the cached result afterwards. Other values are returned as is.
+pub fn force(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let promise = match <[Value; 1]>::try_from(arguments) {
+        Ok([Value::Promise(promise)]) => promise,
+        Ok([value]) => return Ok(value),
+        Err(_) => return Err(argument_error()),
+    };
+
+    let state = std::mem::replace(&mut *promise.state().borrow_mut(), PromiseState::Realizing);
+
+    let result = match state {
+        PromiseState::Pending(thunk) => thunk(interpreter),
+        PromiseState::Realizing => Err(Error::new(
+            "Promise depends on itself",
+            ErrorType::ArgumentError,
+        )),
+        PromiseState::Failed(error) => Err(error),
+        PromiseState::Realized(value) => Ok(value),
+    };
+
+    *promise.state().borrow_mut() = match &result {
+        Ok(value) => PromiseState::Realized(value.clone()),
+        Err(error) => PromiseState::Failed(error.clone()),
+    };
+
+    return result;
+}
+
+/// `(is-realized value)` checks whether a promise has been forced or the
+/// first cell of a lazy sequence has been computed.
+fn is_realized(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::Promise(promise)] => Ok(Value::Boolean(matches!(
+            &*promise.state().borrow(),
+            PromiseState::Realized(_) | PromiseState::Failed(_)
+        ))),
+
+        [Value::LazySequence(sequence)] => Ok(Value::Boolean(matches!(
+            &*sequence.state().borrow(),
+            LazyState::Empty | LazyState::Cons(_, _) | LazyState::Failed(_)
+        ))),
+
+        [value] => Err(type_error(Type::Promise, value)),
+
+        _ => Err(argument_error()),
+    }
+}
+
+/// `(memoize f)` returns a function that calls `f` once for every distinct
+/// list of arguments and remembers the results. `f` can be anything callable,
+/// e.g. a multimethod or a keyword.
+fn memoize(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let function = match <[Value; 1]>::try_from(arguments) {
+        Ok([function]) => function,
+        Err(_) => return Err(argument_error()),
+    };
+
+    let name = match &function {
+        Value::Function(function) => function.identifier().to_string(),
+        Value::Builtin(builtin) => builtin.name().to_string(),
+        Value::Keyword(keyword) => format!(":{}", keyword.name()),
+        Value::MultiMethod(multi_method) => multi_method.name().to_string(),
+        value => return Err(type_error(Type::Function, value)),
+    };
+
+    let cache: Rc<RefCell<HashMap<Vec<Value>, Value>>> = Rc::new(RefCell::new(HashMap::new()));
+
+    let memoized = Builtin::from_closure(&name, move |interpreter, arguments| {
+        if let Some(value) = cache.borrow().get(&arguments) {
+            return Ok(value.clone());
+        }
+
+        let value = interpreter.call_value(&function, arguments.clone())?;
+        cache.borrow_mut().insert(arguments, value.clone());
+
+        Ok(value)
+    });
+
+    return Ok(Value::Builtin(memoized));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::evaluate_source;
+
+    #[test]
+    fn test_delay_and_force() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define promise (let [a 20] (delay (add a 22))))
+             (list (is-realized promise) (force promise) (is-realized promise) (force promise))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(false 42 true 42)".to_string())
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(force 1)"),
+            Ok(Value::Number(1))
+        );
+    }
+
+    #[test]
+    fn test_delay_evaluates_once() {
+        let mut interpreter = Interpreter::new();
+
+        // The body returns a new lazy sequence every time it is evaluated,
+        // and lazy sequences are only equal to themselves.
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define promise (delay (range)))
+             (is-equal (force promise) (force promise))",
+        );
+
+        assert_eq!(result, Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_memoize() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define fib
+               (memoize (fn [n]
+                 (if (is-less n 2) n (add (fib (subtract n 1)) (fib (subtract n 2)))))))
+             (fib 80)",
+        );
+
+        assert_eq!(result, Ok(Value::Number(23416728348467685)));
+    }
+
+    #[test]
+    fn test_memoizing_other_callables() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define calls (atom 0))
+             (defmulti describe (fn [value] (type-of value)))
+             (defmethod describe \"Number\" [value]
+               (let [_ (swap! calls inc)] \"number\"))
+             (define memoized-describe (memoize describe))
+             (define memoized-name (memoize :name))
+             (list (memoized-describe 1) (memoized-describe 1) (deref calls)
+                   (memoized-name {:name \"risp\"}))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(number number 1 risp)".to_string())
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(memoize 1)"),
+            Err(type_error(Type::Function, &Value::Number(1)))
+        );
+    }
+
+    #[test]
+    fn test_memoize_keys_on_argument_values() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define fresh (memoize (fn [a] (range))))
+             (list (is-equal (fresh (list 1)) (fresh (list 1)))
+                   (is-equal (fresh (list 1)) (fresh (list 2))))",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(true false)".to_string())
+        );
+    }
+}
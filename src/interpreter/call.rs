@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use super::{pattern::bind_pattern, Interpreter};
+use crate::{value::Function, ASTNode, Error, ErrorType, FunctionArity, Value};
+
+/// Keyword arguments of a call, written as `:name value` pairs.
+pub type KeywordArguments = Vec<(String, Value)>;
+
+impl Interpreter {
+    /// Calls a user defined function with already evaluated arguments.
+    pub(crate) fn call_function(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+        keyword_arguments: KeywordArguments,
+    ) -> Result<Value, Error> {
+        let arity = select_arity(function, arguments.len())
+            .map_err(|error| error.with_frame(function.identifier(), String::new()))?;
+
+        let variables = self
+            .bind_parameters(function, arity, arguments, keyword_arguments)
+            .map_err(|error| error.with_frame(function.identifier(), String::new()))?;
+
+        self.environment_stack
+            .push_call_environment(variables, function.closure());
+        let previous_file = std::mem::replace(&mut self.current_file, function.file().cloned());
+
+        let result = if self.check_contracts && !arity.conditions.is_empty() {
+            self.evaluate_checked_body(function, arity)
+        } else {
+            self.evaluate(&arity.body)
+        };
+
+        let result = result.map_err(|mut error| {
+            if let Some(file) = function.file() {
+                error = error.in_file(file);
+            }
+
+            let arguments = self.describe_parameters(&arity.parameter_list.names());
+            error.with_frame(function.identifier(), arguments)
+        });
+
+        // Pop the call stack
+        self.environment_stack.pop_environment();
+        self.current_file = previous_file;
+
+        return result;
+    }
+
+    /// Evaluates the body of an arity, checking its conditions before and
+    /// after.
+    fn evaluate_checked_body(
+        &mut self,
+        function: &Function,
+        arity: &FunctionArity,
+    ) -> Result<Value, Error> {
+        for condition in arity.conditions.pre.iter() {
+            self.check_condition(function, condition, false)?;
+        }
+
+        let value = self.evaluate(&arity.body)?;
+
+        if arity.conditions.post.is_empty() {
+            return Ok(value);
+        }
+
+        self.environment_stack
+            .push_environment(HashMap::from([("%".to_string(), value.clone())]));
+        let result = arity
+            .conditions
+            .post
+            .iter()
+            .try_for_each(|condition| self.check_condition(function, condition, true));
+        self.environment_stack.pop_environment();
+
+        return result.map(|_| value);
+    }
+
+    fn check_condition(
+        &mut self,
+        function: &Function,
+        condition: &ASTNode,
+        postcondition: bool,
+    ) -> Result<(), Error> {
+        if self.evaluate(condition)?.is_truthy() {
+            return Ok(());
+        }
+
+        return Err(Error::new(
+            "Contract violation",
+            ErrorType::ContractViolation {
+                function: function.identifier().to_string(),
+                condition: condition.to_string(),
+                postcondition,
+            },
+        ));
+    }
+
+    /// Summarizes the values bound to `names` for a backtrace, e.g.
+    /// `a=1, b=(1 2)`. Long values are cut short.
+    fn describe_parameters(&mut self, names: &[&str]) -> String {
+        const MAXIMUM_LENGTH: usize = 24;
+
+        let mut descriptions = Vec::with_capacity(names.len());
+
+        for name in names.iter() {
+            let value = self.environment_stack.get(name).unwrap_or(Value::Nil);
+            let mut description = value.to_display_string();
+
+            if description.chars().count() > MAXIMUM_LENGTH {
+                description = description.chars().take(MAXIMUM_LENGTH).collect();
+                description.push_str("...");
+            }
+
+            descriptions.push(format!("{}={}", name, description));
+        }
+
+        return descriptions.join(", ");
+    }
+
+    fn bind_parameters(
+        &mut self,
+        function: &Function,
+        arity: &FunctionArity,
+        arguments: Vec<Value>,
+        keyword_arguments: KeywordArguments,
+    ) -> Result<HashMap<String, Value>, Error> {
+        let parameter_list = &arity.parameter_list;
+        let mut variables = HashMap::new();
+        let mut arguments = arguments.into_iter();
+
+        for pattern in parameter_list.required.iter() {
+            bind_pattern(pattern, arguments.next().unwrap(), &mut variables)?;
+        }
+
+        for (name, default) in parameter_list.optional.iter() {
+            let value = match arguments.next() {
+                Some(value) => value,
+                None => self.evaluate_default(function, default, &variables)?,
+            };
+
+            variables.insert(name.to_string(), value);
+        }
+
+        for (name, _) in keyword_arguments.iter() {
+            if !parameter_list
+                .keywords
+                .iter()
+                .any(|(keyword, _)| keyword == name)
+            {
+                return Err(Error::new(
+                    "Unexpected keyword argument",
+                    ErrorType::UnexpectedKeywordArgument(name.to_string()),
+                ));
+            }
+        }
+
+        for (name, default) in parameter_list.keywords.iter() {
+            let value = match keyword_arguments.iter().rev().find(|(key, _)| key == name) {
+                Some((_, value)) => value.clone(),
+                None => self.evaluate_default(function, default, &variables)?,
+            };
+
+            variables.insert(name.to_string(), value);
+        }
+
+        if let Some(rest) = &parameter_list.rest {
+            bind_pattern(rest, Value::List(arguments.collect()), &mut variables)?;
+        }
+
+        return Ok(variables);
+    }
+
+    /// Evaluates the default value of a parameter, which may refer to the
+    /// parameters bound before it.
+    fn evaluate_default(
+        &mut self,
+        function: &Function,
+        default: &Option<ASTNode>,
+        variables: &HashMap<String, Value>,
+    ) -> Result<Value, Error> {
+        match default {
+            Some(expression) => {
+                self.environment_stack
+                    .push_call_environment(variables.clone(), function.closure());
+                let result = self.evaluate(expression);
+                self.environment_stack.pop_environment();
+
+                return result;
+            }
+
+            None => Ok(Value::Nil),
+        }
+    }
+}
+
+fn select_arity(function: &Function, argument_count: usize) -> Result<&FunctionArity, Error> {
+    let arities = function.arities();
+
+    if let Some(arity) = arities
+        .iter()
+        .find(|arity| arity.parameter_list.accepts(argument_count))
+    {
+        return Ok(arity);
+    }
+
+    let minimum_arity = arities
+        .iter()
+        .map(|arity| arity.parameter_list.minimum_arity())
+        .min()
+        .unwrap_or(0);
+
+    if argument_count < minimum_arity {
+        return Err(Error::new(
+            "Too few arguments",
+            ErrorType::TooFewArguments {
+                expected: minimum_arity,
+                given: argument_count,
+            },
+        ));
+    }
+
+    let accepts_more = arities.iter().any(|arity| {
+        arity
+            .parameter_list
+            .maximum_arity()
+            .is_none_or(|maximum| maximum > argument_count)
+    });
+
+    if accepts_more {
+        return Err(Error::new(
+            "No arity for this number of arguments",
+            ErrorType::NoMatchingArity {
+                accepted: arities
+                    .iter()
+                    .map(|arity| {
+                        (
+                            arity.parameter_list.minimum_arity(),
+                            arity.parameter_list.maximum_arity(),
+                        )
+                    })
+                    .collect(),
+                given: argument_count,
+            },
+        ));
+    }
+
+    let maximum_arity = arities
+        .iter()
+        .filter_map(|arity| arity.parameter_list.maximum_arity())
+        .max()
+        .unwrap_or(0);
+
+    return Err(Error::new(
+        "Too many arguments",
+        ErrorType::TooManyArguments {
+            expected: maximum_arity,
+            given: argument_count,
+        },
+    ));
+}
@@ -0,0 +1,592 @@
+use std::rc::Rc;
+
+use super::{builtins::argument_error, builtins::type_error, Interpreter};
+use crate::{value::BuiltinFunction, ASTNode, Error, ErrorType, Type, Value};
+
+pub const DOC_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("doc", doc),
+    ("arglists", arglists),
+    ("source", source),
+    ("fn-name", fn_name),
+];
+
+/// The parameter lists and a description of every builtin function, which
+/// `doc` and `arglists` show since builtins have no `defn` to read them from.
+const BUILTIN_DOCS: &[(&str, &[&str], &str)] = &[
+    ("add", &["[& numbers]"], "Returns the sum of the numbers."),
+    (
+        "subtract",
+        &["[number & numbers]"],
+        "Subtracts the other numbers from the first, or negates a single number.",
+    ),
+    (
+        "multiply",
+        &["[& numbers]"],
+        "Returns the product of the numbers.",
+    ),
+    (
+        "divide",
+        &["[number & numbers]"],
+        "Divides the first number by the others in turn, rounding towards zero.",
+    ),
+    (
+        "remainder",
+        &["[a b]"],
+        "Returns the remainder of dividing a by b, with the sign of a.",
+    ),
+    (
+        "car",
+        &["[list]"],
+        "Returns the first element of the list, or nil.",
+    ),
+    (
+        "cdr",
+        &["[list]"],
+        "Returns the list without its first element.",
+    ),
+    (
+        "is-empty",
+        &["[coll]"],
+        "Checks whether the collection has no elements.",
+    ),
+    (
+        "append",
+        &["[list value]"],
+        "Returns the list with value added at the end.",
+    ),
+    (
+        "prepend",
+        &["[list value]"],
+        "Returns the list with value added at the front.",
+    ),
+    ("is-nil", &["[value]"], "Checks whether the value is nil."),
+    ("is-equal", &["[a b]"], "Checks whether a and b are equal."),
+    ("is-less", &["[a b]"], "Checks whether a is less than b."),
+    (
+        "is-greater",
+        &["[a b]"],
+        "Checks whether a is greater than b.",
+    ),
+    (
+        "get",
+        &["[map key]"],
+        "Returns the value of key in the map, or nil.",
+    ),
+    (
+        "println",
+        &["[& values]"],
+        "Prints the values separated by spaces, followed by a newline.",
+    ),
+    (
+        "str",
+        &["[& values]"],
+        "Concatenates the display strings of the values, leaving out nil.",
+    ),
+    (
+        "read-file",
+        &["[path]"],
+        "Returns the contents of the file as a string.",
+    ),
+    (
+        "parse-number",
+        &["[string]"],
+        "Parses the string as a number.",
+    ),
+    (
+        "apply",
+        &["[f & arguments]"],
+        "Calls f with the arguments, spreading the last one if it is a list.",
+    ),
+    ("atom", &["[value]"], "Creates an atom holding the value."),
+    (
+        "deref",
+        &["[reference]"],
+        "Returns the value of an atom, or forces a promise.",
+    ),
+    (
+        "reset!",
+        &["[atom value]"],
+        "Replaces the value of the atom.",
+    ),
+    (
+        "swap!",
+        &["[atom f & arguments]"],
+        "Sets the atom to (f value arguments...) and returns the new value.",
+    ),
+    (
+        "compare-and-set!",
+        &["[atom old new]"],
+        "Sets the atom to new if its value equals old, and returns whether it did.",
+    ),
+    (
+        "add-watch",
+        &["[atom key f]"],
+        "Calls (f key atom old new) whenever the value of the atom changes.",
+    ),
+    (
+        "remove-watch",
+        &["[atom key]"],
+        "Removes the watch added with key.",
+    ),
+    (
+        "force",
+        &["[value]"],
+        "Forces a promise, returning other values unchanged.",
+    ),
+    (
+        "is-realized",
+        &["[value]"],
+        "Checks whether a promise or lazy sequence has been computed.",
+    ),
+    (
+        "memoize",
+        &["[f]"],
+        "Returns a function that remembers the results of calling f.",
+    ),
+    (
+        "throw",
+        &["[value]"],
+        "Raises the value as an error that try can catch.",
+    ),
+    (
+        "ex-info",
+        &["[message data]"],
+        "Creates an error value carrying a data map.",
+    ),
+    (
+        "ex-message",
+        &["[error]"],
+        "Returns the message of a caught error, or nil.",
+    ),
+    (
+        "ex-data",
+        &["[error]"],
+        "Returns the data map of an error created with ex-info, or nil.",
+    ),
+    (
+        "cons",
+        &["[value seq]"],
+        "Puts the value in front of seq without realizing it.",
+    ),
+    (
+        "iterate",
+        &["[f x]"],
+        "Returns the infinite sequence x, (f x), (f (f x)), ...",
+    ),
+    (
+        "repeat",
+        &["[x]", "[n x]"],
+        "Repeats x forever, or returns a list of n times x.",
+    ),
+    (
+        "cycle",
+        &["[coll]"],
+        "Repeats the elements of coll forever.",
+    ),
+    (
+        "ok",
+        &["[value]"],
+        "Wraps the value in a successful result.",
+    ),
+    ("err", &["[value]"], "Wraps the value in a failed result."),
+    (
+        "is-ok",
+        &["[result]"],
+        "Checks whether the result is an ok.",
+    ),
+    (
+        "is-err",
+        &["[result]"],
+        "Checks whether the result is an err.",
+    ),
+    (
+        "unwrap",
+        &["[result]"],
+        "Returns the value of an ok and throws the value of an err.",
+    ),
+    (
+        "unwrap-or",
+        &["[result default]"],
+        "Returns the value of an ok, or default.",
+    ),
+    (
+        "unwrap-err",
+        &["[result]"],
+        "Returns the value of an err and throws otherwise.",
+    ),
+    (
+        "map-ok",
+        &["[f result]"],
+        "Applies f to the value of an ok, passing an err through.",
+    ),
+    (
+        "map-err",
+        &["[f result]"],
+        "Applies f to the value of an err, passing an ok through.",
+    ),
+    (
+        "attempt",
+        &["[f & arguments]"],
+        "Calls f and returns its value as an ok, or what it raised as an err.",
+    ),
+    (
+        "try-read-file",
+        &["[path]"],
+        "Returns the contents of the file as an ok, or the error as an err.",
+    ),
+    (
+        "try-parse-number",
+        &["[string]"],
+        "Returns the string parsed as a number as an ok, or the error as an err.",
+    ),
+    (
+        "map",
+        &["[f coll & colls]"],
+        "Calls f with an element from every collection, until the shortest ends.",
+    ),
+    (
+        "filter",
+        &["[pred coll]"],
+        "Returns the elements of coll satisfying pred.",
+    ),
+    (
+        "reduce",
+        &["[f coll]", "[f initial coll]"],
+        "Combines the elements of coll with f, starting from initial.",
+    ),
+    (
+        "range",
+        &["[]", "[end]", "[start end]", "[start end step]"],
+        "Returns the numbers from start up to end, or all natural numbers.",
+    ),
+    (
+        "take",
+        &["[n coll]"],
+        "Returns the first n elements of coll.",
+    ),
+    (
+        "drop",
+        &["[n coll]"],
+        "Returns coll without its first n elements.",
+    ),
+    (
+        "reverse",
+        &["[coll]"],
+        "Returns the elements of coll in reverse order.",
+    ),
+    (
+        "nth",
+        &["[coll index]", "[coll index default]"],
+        "Returns the element at index, or default if it is out of bounds.",
+    ),
+    (
+        "length",
+        &["[coll]"],
+        "Returns the number of elements in coll.",
+    ),
+    (
+        "concat",
+        &["[& colls]"],
+        "Returns a list of the elements of every collection.",
+    ),
+    (
+        "sort",
+        &["[coll]", "[less-than coll]"],
+        "Sorts numbers or strings in ascending order, or by less-than.",
+    ),
+    (
+        "zip",
+        &["[& colls]"],
+        "Pairs up the elements of every collection, until the shortest ends.",
+    ),
+    (
+        "flatten",
+        &["[coll]"],
+        "Flattens nested lists into a single list.",
+    ),
+    (
+        "type-of",
+        &["[value]"],
+        "Returns the name of the type of the value as a string.",
+    ),
+    (
+        "doc",
+        &["[f]"],
+        "Returns the parameter lists and docstring of f, or nil.",
+    ),
+    (
+        "arglists",
+        &["[f]"],
+        "Returns the parameter lists of f as a list of strings.",
+    ),
+    (
+        "source",
+        &["[f]"],
+        "Returns the source of a function, or nil for builtins.",
+    ),
+    ("fn-name", &["[f]"], "Returns the name of f."),
+    (
+        "with-meta",
+        &["[value metadata]"],
+        "Returns the function or list with its metadata map replaced.",
+    ),
+    (
+        "meta",
+        &["[value]"],
+        "Returns the metadata map of a value, or nil.",
+    ),
+];
+
+fn builtin_docs(name: &str) -> Option<(&'static [&'static str], &'static str)> {
+    return BUILTIN_DOCS
+        .iter()
+        .find(|(builtin, _, _)| *builtin == name)
+        .map(|(_, arglists, doc)| (*arglists, *doc));
+}
+
+/// The parameter lists of a function, e.g. `["[list]" "[list index]"]`, or
+/// `None` for builtins without docs and values that aren't functions.
+fn function_arglists(value: &Value) -> Result<Option<Vec<String>>, Error> {
+    match value {
+        Value::Function(function) => Ok(Some(
+            function
+                .arities()
+                .iter()
+                .map(|arity| arity.parameter_list.to_string())
+                .collect(),
+        )),
+        Value::Builtin(builtin) => Ok(builtin_docs(builtin.name())
+            .map(|(arglists, _)| arglists.iter().map(|arglist| arglist.to_string()).collect())),
+        Value::MultiMethod(_) => Ok(None),
+        value => Err(type_error(Type::Function, value)),
+    }
+}
+
+/// `(doc f)` returns a call for every parameter list of `f` followed by its
+/// docstring, e.g. `"(car list)\n  Returns the first element..."`.
+fn doc(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let value = match &arguments[..] {
+        [value] => value,
+        _ => return Err(argument_error()),
+    };
+
+    let (name, docstring) = match value {
+        Value::Function(function) => (function.identifier(), function.doc()),
+        Value::Builtin(builtin) => (
+            builtin.name(),
+            builtin_docs(builtin.name()).map(|(_, doc)| doc),
+        ),
+        _ => return Ok(Value::Nil),
+    };
+
+    let arglists = match function_arglists(value)? {
+        Some(arglists) => arglists,
+        None => return Ok(Value::Nil),
+    };
+
+    let mut lines: Vec<String> = arglists
+        .iter()
+        .map(|arglist| {
+            let parameters = &arglist[1..arglist.len() - 1];
+
+            match parameters {
+                "" => format!("({})", name),
+                parameters => format!("({} {})", name, parameters),
+            }
+        })
+        .collect();
+
+    if let Some(docstring) = docstring {
+        lines.push(format!("  {}", docstring));
+    }
+
+    return Ok(Value::String(lines.join("\n")));
+}
+
+/// `(arglists f)` returns the parameter lists of `f` as strings, or nil if
+/// they aren't known.
+fn arglists(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [value] => Ok(function_arglists(value)?
+            .map(|arglists| Value::List(arglists.into_iter().map(Value::String).collect()))
+            .unwrap_or(Value::Nil)),
+        _ => Err(argument_error()),
+    }
+}
+
+/// `(source f)` returns the pretty-printed declaration of a function, or nil
+/// for builtins whose source isn't risp.
+fn source(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let function = match &arguments[..] {
+        [Value::Function(function)] => function,
+        [Value::Builtin(_) | Value::MultiMethod(_)] => return Ok(Value::Nil),
+        [value] => return Err(type_error(Type::Function, value)),
+        _ => return Err(argument_error()),
+    };
+
+    let declaration = match function.identifier() {
+        "fn" => ASTNode::FunctionExpression {
+            arities: function.arities().clone(),
+        },
+        identifier => ASTNode::FunctionDeclaration {
+            identifier: identifier.to_string(),
+            docstring: function.doc().map(Rc::from),
+            arities: function.arities().clone(),
+        },
+    };
+
+    return Ok(Value::String(declaration.to_pretty_string()));
+}
+
+/// `(fn-name f)` returns the name a function was declared with, `"fn"` for
+/// anonymous functions.
+fn fn_name(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::Function(function)] => Ok(Value::String(function.identifier().to_string())),
+        [Value::Builtin(builtin)] => Ok(Value::String(builtin.name().to_string())),
+        [Value::MultiMethod(multi_method)] => Ok(Value::String(multi_method.name().to_string())),
+        [value] => Err(type_error(Type::Function, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+impl Interpreter {
+    /// `(bound? name)` checks whether `name` is defined where it is
+    /// evaluated, without raising an error when it isn't.
+    pub(super) fn evaluate_bound_expression(
+        &mut self,
+        arguments: &[ASTNode],
+    ) -> Result<Value, Error> {
+        match arguments {
+            [ASTNode::Identifier(name)] => {
+                Ok(Value::Boolean(self.environment_stack.get(name).is_some()))
+            }
+            _ => Err(Error::new("Expected a name", ErrorType::ArgumentError)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::builtins::BUILTIN_TABLES;
+    use super::*;
+    use crate::interpreter::tests::evaluate_source;
+
+    fn string(value: &str) -> Value {
+        return Value::String(value.to_string());
+    }
+
+    #[test]
+    fn test_doc() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+        (defn area "The area of a rectangle." [width height] (multiply width height))
+        (doc area)
+        "#;
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, source),
+            Ok(string("(area width height)\n  The area of a rectangle."))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(doc car)"),
+            Ok(string(
+                "(car list)\n  Returns the first element of the list, or nil."
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(doc (fn [x] x))"),
+            Ok(string("(fn x)"))
+        );
+        assert_eq!(evaluate_source(&mut interpreter, "(doc 1)"), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn test_arglists() {
+        let mut interpreter = Interpreter::new();
+        let source = "(defn f ([a] a) ([a & rest] rest)) (arglists f)";
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, source),
+            Ok(Value::List(
+                vec![string("[a]"), string("[a & rest]")].into()
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(arglists nth)"),
+            Ok(Value::List(
+                vec![string("[coll index]"), string("[coll index default]")].into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_every_builtin_has_docs() {
+        let undocumented: Vec<&str> = BUILTIN_TABLES
+            .iter()
+            .flat_map(|table| table.iter())
+            .map(|(name, _)| *name)
+            .filter(|name| builtin_docs(name).is_none())
+            .collect();
+
+        assert_eq!(undocumented, Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_source() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+        (defn clamp "Keeps n between low and high." [n low high]
+          (if (is-less n low) low (if (is-greater n high) high n)))
+        (source clamp)
+        "#;
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, source),
+            Ok(string(
+                "(defn clamp\n  \"Keeps n between low and high.\"\n  [n low high]\n  (if (is-less n low) low (if (is-greater n high) high n)))"
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(source (fn [x] (add x 1)))"),
+            Ok(string("(fn [x] (add x 1))"))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(source car)"),
+            Ok(Value::Nil)
+        );
+    }
+
+    #[test]
+    fn test_fn_name_and_bound() {
+        let mut interpreter = Interpreter::new();
+        let source = "(defn greet [name] (str \"hello \" name)) (fn-name greet)";
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, source),
+            Ok(string("greet"))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(fn-name map)"),
+            Ok(string("map"))
+        );
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(list (bound? greet) (bound? car) (bound? nope))"
+            ),
+            Ok(Value::List(
+                vec![
+                    Value::Boolean(true),
+                    Value::Boolean(true),
+                    Value::Boolean(false)
+                ]
+                .into()
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(let [x 1] (bound? x))"),
+            Ok(Value::Boolean(true))
+        );
+    }
+}
@@ -1,70 +1,337 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
 
-use crate::Value;
+use crate::{
+    value::{Keyword, Map},
+    Value,
+};
 
+/// A shared handle to an environment, captured by closures.
+pub type Scope = Rc<RefCell<Environment>>;
+
+/// The environment a function was declared in.
+#[derive(Clone)]
+pub enum Closure {
+    /// The top level of the program or of a module. It is held weakly since
+    /// it usually binds the function itself, and the interpreter keeps it
+    /// alive anyway.
+    TopLevel(Weak<RefCell<Environment>>),
+
+    /// A local environment, e.g. of a function call or a `let`.
+    Local(Scope),
+}
+
+impl Closure {
+    fn scope(&self) -> Scope {
+        match self {
+            Closure::TopLevel(environment) => environment
+                .upgrade()
+                .expect("top level environments live as long as the interpreter"),
+            Closure::Local(environment) => environment.clone(),
+        }
+    }
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Closure::TopLevel(a), Closure::TopLevel(b)) => Weak::ptr_eq(a, b),
+            (Closure::Local(a), Closure::Local(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// The environments of a running program. Builtins live in a root
+/// environment that encloses the top level of the program and of every
+/// module, so modules don't see the definitions of the program.
 pub struct EnvironmentStack {
-    global_environment: Environment,
-    environments: VecDeque<Environment>,
+    builtin_environment: Scope,
+    top_level_environment: Scope,
+    environments: Vec<Scope>,
+}
+
+/// The environments of the program saved while a module is evaluated.
+pub struct SavedEnvironments {
+    top_level_environment: Scope,
+    environments: Vec<Scope>,
 }
 
 impl EnvironmentStack {
     pub fn new() -> Self {
+        let builtin_environment = Rc::new(RefCell::new(Environment::new(None)));
+
         Self {
-            global_environment: Environment::new(),
-            environments: VecDeque::new(),
+            top_level_environment: Rc::new(RefCell::new(Environment::new(Some(
+                builtin_environment.clone(),
+            )))),
+            builtin_environment,
+            environments: Vec::new(),
         }
     }
 
+    pub fn define_builtin(&mut self, name: &str, value: Value) {
+        self.builtin_environment.borrow_mut().set(name, value);
+    }
+
+    /// Creates an empty top level environment for a module.
+    pub fn new_module_environment(&self) -> Scope {
+        Rc::new(RefCell::new(Environment::new(Some(
+            self.builtin_environment.clone(),
+        ))))
+    }
+
+    /// Makes `environment` the top level until `leave_module` is called
+    /// with the returned environments.
+    pub fn enter_module(&mut self, environment: Scope) -> SavedEnvironments {
+        SavedEnvironments {
+            top_level_environment: std::mem::replace(&mut self.top_level_environment, environment),
+            environments: std::mem::take(&mut self.environments),
+        }
+    }
+
+    pub fn leave_module(&mut self, saved: SavedEnvironments) {
+        self.top_level_environment = saved.top_level_environment;
+        self.environments = saved.environments;
+    }
+
+    /// Makes the bindings of `module` available as `alias/name` in the
+    /// current environment.
+    pub fn set_alias(&mut self, alias: &str, module: Scope) {
+        self.current_environment()
+            .borrow_mut()
+            .aliases
+            .insert(alias.to_string(), module);
+    }
+
+    /// Binds `name` in the innermost environment.
     pub fn set(&mut self, name: &str, value: Value) {
-        if let Some(environment) = self.environments.back_mut() {
-            environment.set(name, value);
-        } else {
-            self.global_environment.set(name, value)
+        self.current_environment().borrow_mut().set(name, value);
+    }
+
+    /// Attaches metadata to the definition of `name` in the innermost
+    /// environment, e.g. `{:private true}`.
+    pub fn set_metadata(&mut self, name: &str, metadata: Rc<Map>) {
+        self.current_environment()
+            .borrow_mut()
+            .metadata
+            .insert(name.to_string(), metadata);
+    }
+
+    /// Rebinds `name` in the innermost environment that already binds it.
+    /// Returns `false` if no environment binds `name`.
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        let mut environment = Some(self.current_environment().clone());
+
+        while let Some(current) = environment {
+            if current.borrow().contains(name) {
+                current.borrow_mut().set(name, value);
+                return true;
+            }
+
+            environment = current.borrow().parent.clone();
         }
+
+        false
     }
 
+    /// Looks `name` up in the innermost environment and then in every
+    /// enclosing one, ending with the builtins. Qualified names such as
+    /// `m/foo` are looked up in the module aliased as `m`.
     pub fn get(&mut self, name: &str) -> Option<Value> {
-        for environment in self.environments.iter() {
-            if environment.contains(name) {
-                return environment.get(name);
+        let mut environment = Some(self.current_environment().clone());
+
+        while let Some(current) = environment {
+            if let Some(value) = current.borrow().get(name) {
+                return Some(value);
+            }
+
+            environment = current.borrow().parent.clone();
+        }
+
+        match name.split_once('/') {
+            Some((alias, member)) if !alias.is_empty() && !member.is_empty() => {
+                self.get_qualified(alias, member)
+            }
+            _ => None,
+        }
+    }
+
+    fn get_qualified(&self, alias: &str, member: &str) -> Option<Value> {
+        let mut environment = Some(self.current_environment().clone());
+
+        while let Some(current) = environment {
+            if let Some(module) = current.borrow().aliases.get(alias) {
+                let module = module.borrow();
+
+                return match module.is_private(member) {
+                    true => None,
+                    false => module.get(member),
+                };
+            }
+
+            environment = current.borrow().parent.clone();
+        }
+
+        None
+    }
+
+    /// The visible name closest to `name` by edit distance, if any is close
+    /// enough to likely be what was meant.
+    pub fn suggest(&self, name: &str) -> Option<String> {
+        let maximum_distance = (name.chars().count() / 3).max(1);
+        let mut best: Option<(usize, String)> = None;
+        let mut environment = Some(self.current_environment().clone());
+
+        while let Some(current) = environment {
+            for candidate in current.borrow().variables.keys() {
+                let distance = edit_distance(name, candidate);
+
+                let is_better = match &best {
+                    Some((best_distance, best_name)) => {
+                        (distance, candidate) < (*best_distance, best_name)
+                    }
+                    None => true,
+                };
+
+                if distance <= maximum_distance && is_better {
+                    best = Some((distance, candidate.to_string()));
+                }
             }
+
+            environment = current.borrow().parent.clone();
         }
 
-        self.global_environment.get(name)
+        return best.map(|(_, name)| name);
     }
 
+    /// Pushes a new environment enclosed by the current one, e.g. for `let`.
     pub fn push_environment(&mut self, variables: HashMap<String, Value>) {
-        self.environments.push_front(Environment { variables });
+        let parent = self.current_environment().clone();
+        self.push_environment_with_parent(variables, Some(parent));
+    }
+
+    /// Pushes a new environment for a function call. The function body is
+    /// enclosed by the environment it was declared in rather than the
+    /// caller's.
+    pub fn push_call_environment(&mut self, variables: HashMap<String, Value>, closure: &Closure) {
+        self.push_environment_with_parent(variables, Some(closure.scope()));
+    }
+
+    /// Makes the environment of `closure` the innermost one again, e.g. to
+    /// resume a generator where it left off.
+    pub fn push_scope(&mut self, closure: &Closure) {
+        self.environments.push(closure.scope());
     }
 
     pub fn pop_environment(&mut self) {
-        self.environments.pop_front();
+        self.environments.pop();
+    }
+
+    /// The environment a function declared right now should capture.
+    pub fn closure_scope(&self) -> Closure {
+        match self.environments.last() {
+            Some(environment) => Closure::Local(environment.clone()),
+            None => Closure::TopLevel(Rc::downgrade(&self.top_level_environment)),
+        }
+    }
+
+    fn current_environment(&self) -> &Scope {
+        self.environments
+            .last()
+            .unwrap_or(&self.top_level_environment)
+    }
+
+    fn push_environment_with_parent(
+        &mut self,
+        variables: HashMap<String, Value>,
+        parent: Option<Scope>,
+    ) {
+        self.environments.push(Rc::new(RefCell::new(Environment {
+            variables,
+            aliases: HashMap::new(),
+            metadata: HashMap::new(),
+            parent,
+        })));
     }
 }
 
-#[derive(Debug)]
-struct Environment {
+pub struct Environment {
     variables: HashMap<String, Value>,
+    /// Modules required under an alias, for qualified names like `m/foo`.
+    aliases: HashMap<String, Scope>,
+    /// The metadata of definitions that have any, by name.
+    metadata: HashMap<String, Rc<Map>>,
+    parent: Option<Scope>,
 }
 
 impl Environment {
-    pub fn new() -> Self {
+    fn new(parent: Option<Scope>) -> Self {
         Self {
             variables: HashMap::new(),
+            aliases: HashMap::new(),
+            metadata: HashMap::new(),
+            parent,
         }
     }
 
+    /// The names bound in this environment itself, in no particular order.
+    pub fn names(&self) -> Vec<String> {
+        self.variables.keys().cloned().collect()
+    }
+
     pub fn contains(&self, name: &str) -> bool {
         self.variables.contains_key(name)
     }
 
+    /// Binds `name`, dropping the metadata of a previous definition.
     pub fn set(&mut self, name: &str, value: Value) {
+        if !self.metadata.is_empty() {
+            self.metadata.remove(name);
+        }
+
         self.variables.insert(name.to_string(), value);
     }
 
     pub fn get(&self, name: &str) -> Option<Value> {
         return self.variables.get(name).cloned();
     }
+
+    pub fn metadata(&self, name: &str) -> Option<&Rc<Map>> {
+        return self.metadata.get(name);
+    }
+
+    /// Whether the definition of `name` is marked `:private`, hiding it
+    /// from modules requiring this one.
+    pub fn is_private(&self, name: &str) -> bool {
+        return self.metadata(name).is_some_and(|metadata| {
+            metadata
+                .get(&Value::Keyword(Keyword::new("private")))
+                .is_some_and(Value::is_truthy)
+        });
+    }
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            row[j + 1] = substitution.min(previous_row[j + 1] + 1).min(row[j] + 1);
+        }
+
+        previous_row = row;
+    }
+
+    return previous_row[b.len()];
 }
 
 #[cfg(test)]
@@ -106,4 +373,85 @@ mod tests {
         environment_stack.pop_environment();
         assert_eq!(environment_stack.get("my-var"), Some(Value::Number(3)));
     }
+
+    #[test]
+    fn test_assigning_to_the_nearest_binding() {
+        let mut environment_stack = EnvironmentStack::new();
+        environment_stack.set("my-var", Value::Number(3));
+
+        let mut arguments = HashMap::new();
+        arguments.insert("other-var".to_string(), Value::Number(2));
+        environment_stack.push_environment(arguments);
+
+        assert!(environment_stack.assign("my-var", Value::Number(5)));
+        assert!(!environment_stack.assign("missing", Value::Number(5)));
+
+        environment_stack.pop_environment();
+        assert_eq!(environment_stack.get("my-var"), Some(Value::Number(5)));
+        assert_eq!(environment_stack.get("missing"), None);
+    }
+
+    #[test]
+    fn test_call_environments_use_the_closure_scope() {
+        let mut environment_stack = EnvironmentStack::new();
+        environment_stack.set("my-var", Value::Number(3));
+
+        let mut variables = HashMap::new();
+        variables.insert("captured".to_string(), Value::Number(1));
+        environment_stack.push_environment(variables);
+        let closure = environment_stack.closure_scope();
+
+        let mut variables = HashMap::new();
+        variables.insert("caller-local".to_string(), Value::Number(2));
+        environment_stack.push_environment(variables);
+
+        environment_stack.push_call_environment(HashMap::new(), &closure);
+        assert_eq!(environment_stack.get("captured"), Some(Value::Number(1)));
+        assert_eq!(environment_stack.get("caller-local"), None);
+        assert_eq!(environment_stack.get("my-var"), Some(Value::Number(3)));
+    }
+
+    #[test]
+    fn test_modules_have_their_own_top_level() {
+        let mut environment_stack = EnvironmentStack::new();
+        environment_stack.define_builtin("builtin", Value::Number(1));
+        environment_stack.set("program-var", Value::Number(2));
+
+        let module = environment_stack.new_module_environment();
+        let saved = environment_stack.enter_module(module.clone());
+        environment_stack.set("module-var", Value::Number(3));
+        assert_eq!(environment_stack.get("builtin"), Some(Value::Number(1)));
+        assert_eq!(environment_stack.get("program-var"), None);
+        environment_stack.leave_module(saved);
+
+        assert_eq!(environment_stack.get("module-var"), None);
+        environment_stack.set_alias("m", module);
+        assert_eq!(
+            environment_stack.get("m/module-var"),
+            Some(Value::Number(3))
+        );
+        assert_eq!(environment_stack.get("m/builtin"), None);
+    }
+
+    #[test]
+    fn test_suggesting_similar_names() {
+        let mut environment_stack = EnvironmentStack::new();
+        environment_stack.set("my-list", Value::Nil);
+        environment_stack.set("other", Value::Nil);
+
+        let mut variables = HashMap::new();
+        variables.insert("counter".to_string(), Value::Nil);
+        environment_stack.push_environment(variables);
+
+        assert_eq!(
+            environment_stack.suggest("my-lsit"),
+            Some("my-list".to_string())
+        );
+        assert_eq!(
+            environment_stack.suggest("countr"),
+            Some("counter".to_string())
+        );
+        assert_eq!(environment_stack.suggest("unrelated"), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
 }
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use super::{
+    builtins::{argument_error, type_error},
+    Interpreter,
+};
+use crate::{
+    value::{BuiltinFunction, Keyword, Map},
+    ASTNode, Error, ErrorType, Type, Value,
+};
+
+pub const EXCEPTION_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("throw", throw),
+    ("ex-info", ex_info),
+    ("ex-message", ex_message),
+    ("ex-data", ex_data),
+];
+
+/// The value a `catch` clause binds for an error. Thrown values are caught
+/// as they are, interpreter errors become a map with a `:kind` and a
+/// `:message`.
+pub fn error_value(error: &Error) -> Value {
+    match error.error_type() {
+        ErrorType::Thrown(value) => value.clone(),
+
+        error_type => exception_map(error_type.kind(), Value::String(error.message()), None),
+    }
+}
+
+fn exception_map(kind: &str, message: Value, data: Option<Value>) -> Value {
+    let mut map = Map::new();
+    map.insert(
+        Value::Keyword(Keyword::new("kind")),
+        Value::String(kind.to_string()),
+    );
+    map.insert(Value::Keyword(Keyword::new("message")), message);
+
+    if let Some(data) = data {
+        map.insert(Value::Keyword(Keyword::new("data")), data);
+    }
+
+    return Value::Map(map);
+}
+
+struct TryExpression<'a> {
+    body: &'a [ASTNode],
+    catch: Option<(&'a str, &'a [ASTNode])>,
+    finally: Option<&'a [ASTNode]>,
+}
+
+/// Splits the arguments of `try` into the body and the optional trailing
+/// `(catch name handler...)` and `(finally cleanup...)` clauses.
+fn parse_try_expression(arguments: &[ASTNode]) -> Result<TryExpression<'_>, Error> {
+    let clause = |node: &ASTNode| match node {
+        ASTNode::CallExpression(callee, _, _) => matches!(
+            callee.as_ref(),
+            ASTNode::Identifier(name) if name == "catch" || name == "finally"
+        ),
+        _ => false,
+    };
+
+    let body_length = arguments.iter().position(clause).unwrap_or(arguments.len());
+
+    let mut expression = TryExpression {
+        body: &arguments[..body_length],
+        catch: None,
+        finally: None,
+    };
+
+    for node in arguments[body_length..].iter() {
+        let (name, clause_arguments) = match node {
+            ASTNode::CallExpression(callee, clause_arguments, _) => match callee.as_ref() {
+                ASTNode::Identifier(name) => (name.as_str(), clause_arguments),
+                _ => return Err(argument_error()),
+            },
+            _ => return Err(argument_error()),
+        };
+
+        match (name, &clause_arguments[..]) {
+            ("catch", [ASTNode::Identifier(binding), handler @ ..])
+                if expression.catch.is_none() && expression.finally.is_none() =>
+            {
+                expression.catch = Some((binding, handler));
+            }
+
+            ("finally", cleanup) if expression.finally.is_none() => {
+                expression.finally = Some(cleanup);
+            }
+
+            _ => return Err(argument_error()),
+        }
+    }
+
+    return Ok(expression);
+}
+
+impl Interpreter {
+    /// `(try body... (catch e handler...) (finally cleanup...))` evaluates
+    /// the body and, if it fails, the handler with `e` bound to the error.
+    /// The cleanup always runs afterwards.
+    pub(super) fn evaluate_try_expression(
+        &mut self,
+        arguments: &[ASTNode],
+    ) -> Result<Value, Error> {
+        let expression = parse_try_expression(arguments)?;
+
+        let mut result = self.evaluate_body(expression.body);
+
+        if let (Err(error), Some((binding, handler))) = (&result, expression.catch) {
+            let mut variables = HashMap::new();
+            variables.insert(binding.to_string(), error_value(error));
+
+            self.environment_stack.push_environment(variables);
+            result = self.evaluate_body(handler);
+            self.environment_stack.pop_environment();
+        }
+
+        if let Some(cleanup) = expression.finally {
+            self.evaluate_body(cleanup)?;
+        }
+
+        return result;
+    }
+
+    fn evaluate_body(&mut self, body: &[ASTNode]) -> Result<Value, Error> {
+        let mut result = Value::Nil;
+
+        for expression in body.iter() {
+            result = self.evaluate(expression)?;
+        }
+
+        return Ok(result);
+    }
+}
+
+/// `(throw value)` raises `value` as an error that `try` can catch.
+fn throw(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [value]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+
+    return Err(Error::new("Thrown", ErrorType::Thrown(value)));
+}
+
+/// `(ex-info message data)` creates an error value carrying a data map.
+fn ex_info(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([message @ Value::String(_), data @ (Value::Map(_) | Value::Nil)]) => {
+            Ok(exception_map("ex-info", message, Some(data)))
+        }
+        Ok([Value::String(_), data]) => Err(type_error(Type::Map, &data)),
+        Ok([message, _]) => Err(type_error(Type::String, &message)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+fn exception_field(arguments: Vec<Value>, field: &str) -> Result<Value, Error> {
+    match <[Value; 1]>::try_from(arguments) {
+        Ok([Value::Map(map)]) => Ok(map
+            .get(&Value::Keyword(Keyword::new(field)))
+            .cloned()
+            .unwrap_or(Value::Nil)),
+        Ok(_) => Ok(Value::Nil),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(ex-message e)` is the message of a caught error, or nil.
+fn ex_message(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    return exception_field(arguments, "message");
+}
+
+/// `(ex-data e)` is the data map of an error created with `ex-info`, or nil.
+fn ex_data(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    return exception_field(arguments, "data");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_throw_and_catch() {
+        assert_eq!(display("(try (throw 42) (catch e (add e 1)))"), "43");
+        assert_eq!(display("(try (add 1 2) (catch e 0))"), "3");
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(throw \"oops\")"),
+            Err(Error::new(
+                "Thrown",
+                ErrorType::Thrown(Value::String("oops".to_string()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_catching_interpreter_errors() {
+        assert_eq!(
+            display("(try (add 1 \"a\") (catch e e))"),
+            "{:kind type-error :message type error: expected Number, got String}"
+        );
+        assert_eq!(
+            display("(try (add 1 \"a\") (catch e (:kind e)))"),
+            "type-error"
+        );
+        assert_eq!(
+            display("(try (nth (list) 3) (catch e (ex-message e)))"),
+            "index out of bounds: index 3 is out of bounds for length 0"
+        );
+    }
+
+    #[test]
+    fn test_ex_info() {
+        assert_eq!(
+            display(
+                "(try (throw (ex-info \"bad input\" {\"value\" 3}))
+                   (catch e (list (ex-message e) (get (ex-data e) \"value\"))))"
+            ),
+            "(bad input 3)"
+        );
+    }
+
+    #[test]
+    fn test_finally_always_runs() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define log (atom (list)))
+             (try (try (throw 1)
+                    (finally (swap! log append \"inner\")))
+               (catch e (swap! log append e))
+               (finally (swap! log append \"outer\")))
+             @log",
+        );
+
+        assert_eq!(
+            result.map(|value| value.to_display_string()),
+            Ok("(inner 1 outer)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_errors_unwind_environments() {
+        let mut interpreter = Interpreter::new();
+
+        let result = evaluate_source(
+            &mut interpreter,
+            "(define a 1)
+             (defn fail [a] (let [b 2] (throw a)))
+             (try (fail 5) (catch e e))
+             a",
+        );
+
+        assert_eq!(result, Ok(Value::Number(1)));
+    }
+}
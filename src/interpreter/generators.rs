@@ -0,0 +1,346 @@
+use std::{path::Path, rc::Rc};
+
+use super::{environment::Closure, lazy::lazy, Interpreter};
+use crate::{value::LazySequence, ASTNode, Error, ErrorType, Value};
+
+/// A generator suspended at a `yield`. Rather than suspending the evaluator,
+/// it keeps the bodies it is in as frames, innermost last, and only evaluates
+/// the expressions between two yields when the next element is realized.
+struct Generator {
+    frames: Vec<Frame>,
+    file: Option<Rc<Path>>,
+}
+
+/// A body of a generator, the index of its next expression and the scope it
+/// is evaluated in.
+struct Frame {
+    expressions: Rc<[ASTNode]>,
+    next: usize,
+    scope: Closure,
+}
+
+/// What evaluating an expression of a generator body leads to.
+enum Step {
+    Continue,
+    /// Evaluation goes on in a nested body, e.g. a branch of an `if`.
+    Enter(Frame),
+    Yield(Value),
+    YieldFrom(Value),
+}
+
+impl Generator {
+    /// Evaluates the generator up to its next `yield` and returns the rest of
+    /// the sequence from there.
+    fn resume(mut self, interpreter: &mut Interpreter) -> Result<Value, Error> {
+        let previous_file = std::mem::replace(&mut interpreter.current_file, self.file.clone());
+        let step = self.run(interpreter);
+        interpreter.current_file = previous_file;
+
+        let step = step.map_err(|mut error| {
+            if let Some(file) = &self.file {
+                error = error.in_file(file);
+            }
+
+            error.with_frame("generator", String::new())
+        })?;
+
+        match step {
+            Some(Step::Yield(value)) => Ok(Value::LazySequence(LazySequence::cons(
+                value,
+                self.into_sequence(),
+            ))),
+
+            // A `yield-from` ending the generator becomes the rest of the
+            // sequence, so generators recursing this way run in constant
+            // space.
+            Some(Step::YieldFrom(sequence)) if self.is_finished() => Ok(sequence),
+            Some(Step::YieldFrom(sequence)) => Ok(yield_from(sequence, self)),
+
+            _ => Ok(Value::Nil),
+        }
+    }
+
+    /// Evaluates expressions until one yields, or returns `None` once the
+    /// body is done.
+    fn run(&mut self, interpreter: &mut Interpreter) -> Result<Option<Step>, Error> {
+        while let Some(frame) = self.frames.last_mut() {
+            if frame.next == frame.expressions.len() {
+                self.frames.pop();
+                continue;
+            }
+
+            let expressions = frame.expressions.clone();
+            let index = frame.next;
+            frame.next += 1;
+
+            interpreter.environment_stack.push_scope(&frame.scope);
+            let step = interpreter.generator_step(&expressions[index]);
+            interpreter.environment_stack.pop_environment();
+
+            match step? {
+                Step::Continue => (),
+                Step::Enter(frame) => self.frames.push(frame),
+                step => return Ok(Some(step)),
+            }
+        }
+
+        return Ok(None);
+    }
+
+    fn is_finished(&self) -> bool {
+        return self
+            .frames
+            .iter()
+            .all(|frame| frame.next == frame.expressions.len());
+    }
+
+    fn into_sequence(self) -> Value {
+        return lazy(move |interpreter| self.resume(interpreter));
+    }
+}
+
+/// The elements of `sequence` followed by those the generator yields next.
+fn yield_from(sequence: Value, generator: Generator) -> Value {
+    lazy(
+        move |interpreter| match interpreter.sequence_step(sequence)? {
+            Some((first, rest)) => Ok(Value::LazySequence(LazySequence::cons(
+                first,
+                yield_from(rest, generator),
+            ))),
+            None => generator.resume(interpreter),
+        },
+    )
+}
+
+fn is_yield(callee: &ASTNode) -> bool {
+    return matches!(callee, ASTNode::Identifier(name) if name == "yield" || name == "yield-from");
+}
+
+/// Whether `node` yields where a generator can suspend: directly, in a branch
+/// of an `if` or in the body of a `let`.
+fn yields(node: &ASTNode) -> bool {
+    match node {
+        ASTNode::CallExpression(callee, _, _) => is_yield(callee),
+        ASTNode::IfExpression {
+            when_true,
+            when_false,
+            ..
+        } => yields(when_true) || yields(when_false),
+        ASTNode::LetExpression { body, .. } => body.iter().any(yields),
+        _ => false,
+    }
+}
+
+/// The error for a `yield` evaluated outside of the places a generator can
+/// suspend at, e.g. in the argument of a call.
+pub fn misplaced_yield(name: &str) -> Error {
+    return Error::new(
+        "Misplaced yield",
+        ErrorType::MisplacedYield(name.to_string()),
+    );
+}
+
+impl Interpreter {
+    /// `(generator body...)` returns a lazy sequence of the values the body
+    /// passes to `yield`, evaluating it as far as the realized elements need.
+    /// `(yield-from sequence)` yields every element of a sequence.
+    pub(super) fn evaluate_generator_expression(&mut self, body: &[ASTNode]) -> Value {
+        // The body gets its own environment, like the body of a function.
+        self.environment_stack.push_environment(Default::default());
+        let scope = self.environment_stack.closure_scope();
+        self.environment_stack.pop_environment();
+
+        let generator = Generator {
+            frames: vec![Frame {
+                expressions: Rc::from(body),
+                next: 0,
+                scope,
+            }],
+            file: self.current_file.clone(),
+        };
+
+        return generator.into_sequence();
+    }
+
+    /// Evaluates an expression of a generator body in the current
+    /// environment.
+    fn generator_step(&mut self, expression: &ASTNode) -> Result<Step, Error> {
+        match expression {
+            ASTNode::CallExpression(callee, arguments, _) if is_yield(callee) => {
+                let [argument] = &arguments[..] else {
+                    return Err(Error::new(
+                        "Wrong number of arguments",
+                        ErrorType::ArgumentError,
+                    ));
+                };
+
+                let value = self.evaluate(argument)?;
+
+                match &**callee {
+                    ASTNode::Identifier(name) if name == "yield" => Ok(Step::Yield(value)),
+                    _ => Ok(Step::YieldFrom(value)),
+                }
+            }
+
+            ASTNode::IfExpression {
+                expression,
+                when_true,
+                when_false,
+            } if yields(when_true) || yields(when_false) => {
+                let branch = match self.evaluate(expression)?.is_truthy() {
+                    true => when_true,
+                    false => when_false,
+                };
+
+                Ok(Step::Enter(Frame {
+                    expressions: Rc::from(vec![(**branch).clone()]),
+                    next: 0,
+                    scope: self.environment_stack.closure_scope(),
+                }))
+            }
+
+            ASTNode::LetExpression { bindings, body } if body.iter().any(yields) => {
+                let mut pushed_environments = 0;
+                let result =
+                    self.evaluate_let_expression(bindings, &[], false, &mut pushed_environments);
+                let scope = self.environment_stack.closure_scope();
+
+                for _ in 0..pushed_environments {
+                    self.environment_stack.pop_environment();
+                }
+
+                result?;
+
+                Ok(Step::Enter(Frame {
+                    expressions: Rc::from(&body[..]),
+                    next: 0,
+                    scope,
+                }))
+            }
+
+            expression => self.evaluate(expression).map(|_| Step::Continue),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_yielding_values() {
+        assert_eq!(
+            display("(generator (yield 1) (yield 2) (yield 3))"),
+            "(1 2 3)"
+        );
+        assert_eq!(display("(generator)"), "()");
+        assert_eq!(
+            display(
+                "(defn countdown [n]
+                   (generator
+                     (if (is-greater n 0)
+                         (let [next (subtract n 1)]
+                           (yield n)
+                           (yield-from (countdown next)))
+                         (yield \"liftoff\"))))
+                 (countdown 3)"
+            ),
+            "(3 2 1 liftoff)"
+        );
+    }
+
+    #[test]
+    fn test_generators_are_lazy() {
+        let mut interpreter = Interpreter::new();
+        let source = "
+(define steps (atom 0))
+(define numbers
+  (generator
+    (swap! steps (fn [n] (add n 1)))
+    (yield 1)
+    (swap! steps (fn [n] (add n 1)))
+    (yield 2)
+    (swap! steps (fn [n] (add n 1)))))
+(list (car numbers) (deref steps))";
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, source)
+                .unwrap()
+                .to_display_string(),
+            "(1 1)"
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(list (length numbers) (deref steps))")
+                .unwrap()
+                .to_display_string(),
+            "(2 3)"
+        );
+    }
+
+    #[test]
+    fn test_infinite_generators() {
+        let naturals =
+            "(defn naturals [n] (generator (yield n) (yield-from (naturals (add n 1)))))";
+
+        assert_eq!(
+            display(&format!("{} (take 3 (naturals 0))", naturals)),
+            "(0 1 2)"
+        );
+        assert_eq!(
+            display(&format!("{} (take 1 (drop 100000 (naturals 0)))", naturals)),
+            "(100000)"
+        );
+    }
+
+    #[test]
+    fn test_walking_trees() {
+        assert_eq!(
+            display(
+                "(defn leaves [tree]
+                   (generator
+                     (if (is-equal (type-of tree) \"List\")
+                         (let [[left right] tree]
+                           (yield-from (leaves left))
+                           (yield \"|\")
+                           (yield-from (leaves right)))
+                         (yield tree))))
+                 (leaves (list (list 1 2) (list 3 (list 4 5))))"
+            ),
+            "(1 | 2 | 3 | 4 | 5)"
+        );
+    }
+
+    #[test]
+    fn test_misplaced_yields() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(yield 1)"),
+            Err(Error::new(
+                "Misplaced yield",
+                ErrorType::MisplacedYield("yield".to_string())
+            ))
+        );
+
+        let error = evaluate_source(
+            &mut interpreter,
+            "(car (generator (add 1 (yield-from (list 1)))))",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.error_type(),
+            &ErrorType::MisplacedYield("yield-from".to_string())
+        );
+        assert_eq!(
+            error.to_string(),
+            "misplaced yield: yield-from can only be used in the body of a generator, \
+             the branches of an if or the body of a let inside it in call to generator"
+        );
+        assert_eq!(
+            display("(try (yield 1) (catch e (:kind e)))"),
+            "misplaced-yield"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use std::rc::Rc;
+
+use super::{
+    builtins::{argument_error, type_error},
+    sequences::sequence_values,
+    Interpreter,
+};
+use crate::{
+    value::{BuiltinFunction, LazySequence, LazyState},
+    Error, ErrorType, Type, Value,
+};
+
+pub const LAZY_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("cons", cons),
+    ("iterate", iterate),
+    ("repeat", repeat),
+    ("cycle", cycle),
+];
+
+impl Interpreter {
+    /// Splits a sequence into its first element and the rest of it, or
+    /// `None` if it is empty. Lazy sequences are realized one cell at a time.
+    pub(crate) fn sequence_step(&mut self, value: Value) -> Result<Option<(Value, Value)>, Error> {
+        match value {
+            Value::LazySequence(sequence) => self.realize_cell(&sequence),
+
+            Value::List(list) => {
+                if list.is_empty() {
+                    Ok(None)
+                } else {
+                    let values: Rc<Vec<Value>> = Rc::new(list.into());
+                    Ok(Some((values[0].clone(), list_rest(values, 1))))
+                }
+            }
+
+            Value::Nil => Ok(None),
+
+            value => Err(type_error(Type::List, &value)),
+        }
+    }
+
+    fn realize_cell(&mut self, sequence: &LazySequence) -> Result<Option<(Value, Value)>, Error> {
+        let state = std::mem::replace(&mut *sequence.state().borrow_mut(), LazyState::Realizing);
+
+        let realized = match state {
+            LazyState::Pending(thunk) => thunk(self).and_then(|value| self.sequence_step(value)),
+
+            LazyState::Realizing => Err(Error::new(
+                "Lazy sequence depends on itself",
+                ErrorType::ArgumentError,
+            )),
+
+            LazyState::Failed(error) => Err(error),
+
+            LazyState::Empty => Ok(None),
+
+            LazyState::Cons(first, rest) => Ok(Some((first, rest))),
+        };
+
+        *sequence.state().borrow_mut() = match &realized {
+            Ok(Some((first, rest))) => LazyState::Cons(first.clone(), rest.clone()),
+            Ok(None) => LazyState::Empty,
+            Err(error) => LazyState::Failed(error.clone()),
+        };
+
+        return realized;
+    }
+
+    /// Fully realizes every lazy sequence inside `value`, turning them into
+    /// lists. This does not return for infinite sequences.
+    pub fn realize(&mut self, value: Value) -> Result<Value, Error> {
+        match value {
+            Value::LazySequence(_) | Value::List(_) => {
+                let values = sequence_values(self, value)?
+                    .into_iter()
+                    .map(|value| self.realize(value))
+                    .collect::<Result<Vec<Value>, Error>>()?;
+
+                Ok(Value::List(values.into()))
+            }
+
+            value => Ok(value),
+        }
+    }
+}
+
+/// The elements of a list from `index` on. The cells share the list, so
+/// stepping through it doesn't shift the remaining elements every time.
+fn list_rest(values: Rc<Vec<Value>>, index: usize) -> Value {
+    if index == values.len() {
+        return Value::Nil;
+    }
+
+    lazy(move |_interpreter| {
+        let first = values[index].clone();
+
+        Ok(Value::LazySequence(LazySequence::cons(
+            first,
+            list_rest(values, index + 1),
+        )))
+    })
+}
+
+/// Wraps a thunk producing a sequence in a lazy sequence value.
+pub fn lazy(thunk: impl FnOnce(&mut Interpreter) -> Result<Value, Error> + 'static) -> Value {
+    Value::LazySequence(LazySequence::new(Box::new(thunk)))
+}
+
+/// Steps every sequence at once, returning their first elements and rests,
+/// or `None` once any of them is empty.
+fn step_all(
+    interpreter: &mut Interpreter,
+    sequences: Vec<Value>,
+) -> Result<Option<Vec<(Value, Value)>>, Error> {
+    let mut steps = Vec::with_capacity(sequences.len());
+
+    for sequence in sequences {
+        match interpreter.sequence_step(sequence)? {
+            Some(step) => steps.push(step),
+            None => return Ok(None),
+        }
+    }
+
+    return Ok(Some(steps));
+}
+
+pub fn lazy_map(function: Value, sequences: Vec<Value>) -> Value {
+    lazy(move |interpreter| {
+        let Some(steps) = step_all(interpreter, sequences)? else {
+            return Ok(Value::Nil);
+        };
+        let (firsts, rests): (Vec<Value>, Vec<Value>) = steps.into_iter().unzip();
+
+        let value = interpreter.call_value(&function, firsts)?;
+
+        Ok(Value::LazySequence(LazySequence::cons(
+            value,
+            lazy_map(function, rests),
+        )))
+    })
+}
+
+/// Lists of one element from every sequence at a time, as long as all of
+/// them have one.
+pub fn lazy_zip(sequences: Vec<Value>) -> Value {
+    lazy(move |interpreter| {
+        let Some(steps) = step_all(interpreter, sequences)? else {
+            return Ok(Value::Nil);
+        };
+        let (firsts, rests): (Vec<Value>, Vec<Value>) = steps.into_iter().unzip();
+
+        Ok(Value::LazySequence(LazySequence::cons(
+            Value::List(firsts.into()),
+            lazy_zip(rests),
+        )))
+    })
+}
+
+/// The elements of every sequence in turn. The sequences are in reverse
+/// order, so the next one to step through is the last.
+pub fn lazy_concat(mut sequences: Vec<Value>) -> Value {
+    lazy(move |interpreter| {
+        while let Some(sequence) = sequences.pop() {
+            if let Some((first, rest)) = interpreter.sequence_step(sequence)? {
+                sequences.push(rest);
+
+                return Ok(Value::LazySequence(LazySequence::cons(
+                    first,
+                    lazy_concat(sequences),
+                )));
+            }
+        }
+
+        Ok(Value::Nil)
+    })
+}
+
+pub fn lazy_filter(predicate: Value, sequence: Value) -> Value {
+    lazy(move |interpreter| {
+        let mut sequence = sequence;
+
+        while let Some((first, rest)) = interpreter.sequence_step(sequence)? {
+            if interpreter
+                .call_value(&predicate, vec![first.clone()])?
+                .is_truthy()
+            {
+                return Ok(Value::LazySequence(LazySequence::cons(
+                    first,
+                    lazy_filter(predicate, rest),
+                )));
+            }
+
+            sequence = rest;
+        }
+
+        Ok(Value::Nil)
+    })
+}
+
+pub fn lazy_take(count: i64, sequence: Value) -> Value {
+    lazy(move |interpreter| {
+        if count <= 0 {
+            return Ok(Value::Nil);
+        }
+
+        match interpreter.sequence_step(sequence)? {
+            Some((first, rest)) => Ok(Value::LazySequence(LazySequence::cons(
+                first,
+                lazy_take(count - 1, rest),
+            ))),
+            None => Ok(Value::Nil),
+        }
+    })
+}
+
+pub fn lazy_drop(count: i64, sequence: Value) -> Value {
+    lazy(move |interpreter| {
+        let mut sequence = sequence;
+
+        for _ in 0..count {
+            match interpreter.sequence_step(sequence)? {
+                Some((_, rest)) => sequence = rest,
+                None => return Ok(Value::Nil),
+            }
+        }
+
+        Ok(sequence)
+    })
+}
+
+/// The numbers from `start` by `step`, up to the bounds of a number.
+pub fn lazy_range(start: i64, step: i64) -> Value {
+    lazy(move |_interpreter| {
+        let rest = match start.checked_add(step) {
+            Some(next) => lazy_range(next, step),
+            None => Value::Nil,
+        };
+
+        Ok(Value::LazySequence(LazySequence::cons(
+            Value::Number(start),
+            rest,
+        )))
+    })
+}
+
+/// `(cons value seq)` puts `value` in front of `seq` without realizing it.
+fn cons(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [first, rest]: [Value; 2] = arguments.try_into().map_err(|_| argument_error())?;
+
+    match rest {
+        Value::List(values) => {
+            let mut new_values = Vec::with_capacity(values.len() + 1);
+            new_values.push(first);
+            new_values.extend(values);
+            Ok(Value::List(new_values.into()))
+        }
+
+        Value::Nil => Ok(Value::List(vec![first].into())),
+
+        Value::LazySequence(_) => Ok(Value::LazySequence(LazySequence::cons(first, rest))),
+
+        rest => Err(type_error(Type::List, &rest)),
+    }
+}
+
+fn iterate_from(function: Value, value: Value) -> Value {
+    Value::LazySequence(LazySequence::cons(
+        value.clone(),
+        lazy(move |interpreter| {
+            let next = interpreter.call_value(&function, vec![value])?;
+            Ok(iterate_from(function, next))
+        }),
+    ))
+}
+
+/// `(iterate f x)` is the infinite sequence `x`, `(f x)`, `(f (f x))`, ...
+fn iterate(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [function, value]: [Value; 2] = arguments.try_into().map_err(|_| argument_error())?;
+
+    return Ok(iterate_from(function, value));
+}
+
+fn repeat_forever(value: Value) -> Value {
+    lazy(move |_interpreter| {
+        Ok(Value::LazySequence(LazySequence::cons(
+            value.clone(),
+            repeat_forever(value),
+        )))
+    })
+}
+
+/// `(repeat x)` repeats `x` forever, `(repeat n x)` returns a list of `n` x.
+fn repeat(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([Value::Number(count), value]) => {
+            Ok(Value::List(vec![value; count.max(0) as usize].into()))
+        }
+
+        Ok([count, _]) => Err(type_error(Type::Number, &count)),
+
+        Err(arguments) => match <[Value; 1]>::try_from(arguments) {
+            Ok([value]) => Ok(repeat_forever(value)),
+            Err(_) => Err(argument_error()),
+        },
+    }
+}
+
+fn cycle_from(values: Rc<Vec<Value>>, index: usize) -> Value {
+    lazy(move |_interpreter| {
+        let first = values[index].clone();
+        let next = (index + 1) % values.len();
+
+        Ok(Value::LazySequence(LazySequence::cons(
+            first,
+            cycle_from(values, next),
+        )))
+    })
+}
+
+/// `(cycle coll)` repeats the elements of `coll` forever.
+fn cycle(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [collection]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+    let values = sequence_values(interpreter, collection)?;
+
+    if values.is_empty() {
+        return Ok(Value::List(values.into()));
+    }
+
+    return Ok(cycle_from(Rc::new(values), 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_infinite_sequences() {
+        assert_eq!(display("(take 3 (range))"), "(0 1 2)");
+        assert_eq!(
+            display("(take 4 (iterate (fn [a] (add a a)) 1))"),
+            "(1 2 4 8)"
+        );
+        assert_eq!(display("(take 3 (repeat \"a\"))"), "(a a a)");
+        assert_eq!(display("(repeat 2 1)"), "(1 1)");
+        assert_eq!(display("(take 5 (cycle (list 1 2)))"), "(1 2 1 2 1)");
+    }
+
+    #[test]
+    fn test_lazy_seq() {
+        assert_eq!(
+            display(
+                "(defn naturals [n] (lazy-seq (cons n (naturals (add n 1)))))
+                 (take 3 (drop 10 (naturals 0)))"
+            ),
+            "(10 11 12)"
+        );
+    }
+
+    #[test]
+    fn test_lazy_map_and_filter() {
+        assert_eq!(
+            display(
+                "(take 3 (filter (fn [a] (is-greater a 5))
+                                 (map (fn [a] (add a a)) (range))))"
+            ),
+            "(6 8 10)"
+        );
+        assert_eq!(display("(reduce add (take 100000 (range)))"), "4999950000");
+        assert_eq!(
+            display("(reduce add (map add (range) (range 100000)))"),
+            "9999900000"
+        );
+    }
+
+    #[test]
+    fn test_list_functions_on_lazy_sequences() {
+        assert_eq!(
+            display(
+                "(define numbers (range))
+                 (list (car numbers) (car (cdr numbers)) (is-empty numbers)
+                       (is-empty (take 0 numbers)))"
+            ),
+            "(0 1 false true)"
+        );
+    }
+
+    #[test]
+    fn test_ranges_end_at_the_largest_number() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            interpreter
+                .realize(lazy_range(i64::MAX - 1, 1))
+                .unwrap()
+                .to_display_string(),
+            "(9223372036854775806 9223372036854775807)"
+        );
+    }
+
+    #[test]
+    fn test_sequence_functions_on_infinite_ranges() {
+        assert_eq!(display("(nth (range) 5)"), "5");
+        assert_eq!(display("(nth (take 2 (range)) 5 nil)"), "nil");
+        assert_eq!(display("(nth (range) -1 nil)"), "nil");
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(nth (range) -1)"),
+            Err(Error::new(
+                "Index out of bounds",
+                ErrorType::IndexOutOfBounds {
+                    index: -1,
+                    length: 0
+                }
+            ))
+        );
+        assert_eq!(
+            display("(take 4 (concat (list 1) \"ab\" (range)))"),
+            "(1 a b 0)"
+        );
+        assert_eq!(
+            display("(take 2 (zip (range) (list 1 2 3) (range 10 20)))"),
+            "((0 1 10) (1 2 11))"
+        );
+    }
+
+    #[test]
+    fn test_elements_are_realized_once() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(
+            &mut interpreter,
+            "(define numbers (map (fn [a] (list a)) (take 2 (range))))
+             (car numbers)",
+        )
+        .unwrap();
+
+        let numbers = interpreter.environment_stack.get("numbers").unwrap();
+        assert_eq!(numbers.to_display_string(), "((0) ...)");
+    }
+}
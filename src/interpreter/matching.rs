@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use super::{builtins::argument_error, Interpreter};
+use crate::{tokenizer::Span, value::Keyword, ASTNode, Error, ErrorType, Value, Warning};
+
+/// The name of a variant without the alias it may be qualified with, e.g.
+/// `Circle` for `shapes/Circle`.
+fn unqualified(name: &str) -> &str {
+    return name.rsplit_once('/').map_or(name, |(_, name)| name);
+}
+
+/// Whether a bare name in a pattern stands for a variant of the value's
+/// type, such as `Empty` in `(deftype Tree Empty (Node left right))`,
+/// rather than for a new binding.
+fn is_variant_name(name: &str, value: &Value) -> bool {
+    match value {
+        Value::Record(record) => record.record_type().sum_type().is_some_and(|sum_type| {
+            sum_type
+                .variants()
+                .iter()
+                .any(|variant| variant == unqualified(name))
+        }),
+        _ => false,
+    }
+}
+
+/// Matches `value` against a pattern, collecting the variables it binds.
+/// Patterns are `_`, names, literals, keywords and `(Variant pattern...)`.
+fn match_pattern(
+    pattern: &ASTNode,
+    value: &Value,
+    variables: &mut HashMap<String, Value>,
+) -> Result<bool, Error> {
+    match pattern {
+        ASTNode::Identifier(name) if name == "_" => Ok(true),
+
+        ASTNode::Identifier(name) if is_variant_name(name, value) => match value {
+            Value::Record(record) => Ok(record.record_type().name() == unqualified(name)),
+            _ => Ok(false),
+        },
+
+        ASTNode::Identifier(name) => {
+            variables.insert(name.to_string(), value.clone());
+            Ok(true)
+        }
+
+        ASTNode::NumberLiteral(number) => Ok(*value == Value::Number(*number)),
+        ASTNode::StringLiteral(string) => Ok(matches!(value, Value::String(s) if s == string)),
+        ASTNode::BooleanLiteral(boolean) => Ok(*value == Value::Boolean(*boolean)),
+        ASTNode::NilLiteral => Ok(value.is_nil()),
+        ASTNode::KeywordLiteral(name) => Ok(*value == Value::Keyword(Keyword::new(name))),
+
+        ASTNode::CallExpression(callee, patterns, _) => {
+            let name = match callee.as_ref() {
+                ASTNode::Identifier(name) => unqualified(name),
+                _ => return Err(Error::new("Expected a pattern", ErrorType::ArgumentError)),
+            };
+
+            let record = match value {
+                Value::Record(record) if record.record_type().name() == name => record,
+                _ => return Ok(false),
+            };
+
+            if patterns.len() != record.record_type().field_names().len() {
+                return Err(Error::new(
+                    "Expected a pattern for every field",
+                    ErrorType::ArgumentError,
+                ));
+            }
+
+            for (pattern, (_, value)) in patterns.iter().zip(record.fields()) {
+                if !match_pattern(pattern, value, variables)? {
+                    return Ok(false);
+                }
+            }
+
+            Ok(true)
+        }
+
+        _ => Err(Error::new("Expected a pattern", ErrorType::ArgumentError)),
+    }
+}
+
+/// The variants of the value's type that no clause handles. Only the outer
+/// pattern of every clause is considered.
+fn missing_variants(value: &Value, patterns: &[&ASTNode]) -> Vec<String> {
+    let sum_type = match value {
+        Value::Record(record) => match record.record_type().sum_type() {
+            Some(sum_type) => sum_type,
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    let mut handled = Vec::new();
+
+    for pattern in patterns.iter() {
+        match pattern {
+            ASTNode::Identifier(name) if is_variant_name(name, value) => {
+                handled.push(unqualified(name))
+            }
+            ASTNode::Identifier(_) => return Vec::new(),
+            ASTNode::CallExpression(callee, _, _) => {
+                if let ASTNode::Identifier(name) = callee.as_ref() {
+                    handled.push(unqualified(name));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    return sum_type
+        .variants()
+        .iter()
+        .filter(|variant| !handled.contains(&variant.as_str()))
+        .cloned()
+        .collect();
+}
+
+impl Interpreter {
+    /// `(match value pattern body pattern body...)` evaluates the body of
+    /// the first clause whose pattern matches, with the variables the pattern
+    /// binds.
+    pub(super) fn evaluate_match_expression(
+        &mut self,
+        arguments: &[ASTNode],
+        span: Span,
+    ) -> Result<Value, Error> {
+        let (subject, clauses) = arguments.split_first().ok_or_else(argument_error)?;
+
+        if clauses.is_empty() || clauses.len() % 2 != 0 {
+            return Err(Error::new(
+                "Expected a pattern and a body for every clause",
+                ErrorType::ArgumentError,
+            ));
+        }
+
+        let value = self.evaluate(subject)?;
+
+        let patterns: Vec<&ASTNode> = clauses.iter().step_by(2).collect();
+        self.warn_about_missing_variants(&value, &patterns, span);
+
+        for clause in clauses.chunks(2) {
+            let mut variables = HashMap::new();
+
+            if match_pattern(&clause[0], &value, &mut variables)? {
+                self.environment_stack.push_environment(variables);
+                let result = self.evaluate(&clause[1]);
+                self.environment_stack.pop_environment();
+
+                return result;
+            }
+        }
+
+        return Err(Error::new("No match", ErrorType::NoMatch(value)));
+    }
+
+    /// Warns once per `match` that doesn't handle every variant of the type
+    /// of the value it matched.
+    fn warn_about_missing_variants(&mut self, value: &Value, patterns: &[&ASTNode], span: Span) {
+        let missing = missing_variants(value, patterns);
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let type_name = match value {
+            Value::Record(record) => record
+                .record_type()
+                .sum_type()
+                .map_or("", |sum_type| sum_type.name()),
+            _ => "",
+        };
+
+        self.warn(
+            format!(
+                "non-exhaustive match on {}, missing {}",
+                type_name,
+                missing.join(", ")
+            ),
+            span,
+        );
+    }
+
+    /// Returns the warnings raised since the last call, oldest first.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        return std::mem::take(&mut self.warnings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    const SHAPES: &str = "(deftype Shape (Circle r) (Rect w h) Empty)
+         (defn area [shape]
+           (match shape
+             (Circle r) (multiply 3 r r)
+             (Rect w h) (multiply w h)
+             Empty 0))";
+
+    #[test]
+    fn test_variants() {
+        assert_eq!(
+            display(&format!(
+                "{} (list (Circle 2) Empty (shape? (Rect 1 2)) (circle? (Rect 1 2)) (rect-h (Rect 1 2)))",
+                SHAPES
+            )),
+            "(#Circle{:r 2} #Empty true false 2)"
+        );
+    }
+
+    #[test]
+    fn test_matching_variants() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(&mut interpreter, SHAPES).unwrap();
+
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(map area (list (Circle 2) (Rect 2 3) Empty))"
+            )
+            .map(|value| value.to_display_string()),
+            Ok("(12 6 0)".to_string())
+        );
+        assert!(interpreter.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_matching_literals_and_nested_patterns() {
+        assert_eq!(
+            display(
+                "(deftype Expression (Literal n) (Sum a b))
+                 (defn evaluate [e]
+                   (match e
+                     (Sum (Literal 0) b) (evaluate b)
+                     (Sum a b) (add (evaluate a) (evaluate b))
+                     (Literal n) n))
+                 (list (evaluate (Sum (Literal 0) (Literal 2)))
+                       (evaluate (Sum (Literal 1) (Sum (Literal 2) (Literal 3))))
+                       (match \"b\" \"a\" 1 \"b\" 2)
+                       (match 5 1 \"one\" n (add n 1)))"
+            ),
+            "(2 6 2 6)"
+        );
+    }
+
+    #[test]
+    fn test_no_match() {
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(match 3 1 true 2 false)"),
+            Err(Error::new("No match", ErrorType::NoMatch(Value::Number(3))))
+        );
+    }
+
+    #[test]
+    fn test_exhaustiveness_warnings() {
+        let mut interpreter = Interpreter::new();
+
+        evaluate_source(
+            &mut interpreter,
+            "(deftype Light Red Yellow Green)
+             (defn next [light]
+               (match light
+                 Red Green
+                 Green Yellow))
+             (next Red)
+             (next Green)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interpreter.take_warnings(),
+            vec![Warning {
+                message: "non-exhaustive match on Light, missing Yellow".to_string(),
+                span: Span {
+                    line: 3,
+                    column: 16
+                },
+                file: None,
+            }]
+        );
+
+        evaluate_source(
+            &mut interpreter,
+            "(defn stop [light] (match light Red true _ false))
+             (stop Yellow)",
+        )
+        .unwrap();
+
+        assert!(interpreter.take_warnings().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use std::rc::Rc;
+
+use super::{builtins::argument_error, builtins::type_error, Interpreter};
+use crate::{
+    tokenizer::Span,
+    value::{BuiltinFunction, Keyword, Map},
+    ASTNode, Error, ErrorType, Type, Value,
+};
+
+pub const METADATA_BUILTINS: &[(&str, BuiltinFunction)] =
+    &[("with-meta", with_meta), ("meta", meta)];
+
+/// The forms whose first argument names what they define, so that metadata
+/// written before them applies to the definition.
+const DEFINING_FORMS: &[&str] = &["define", "defmulti", "defprotocol", "defrecord", "deftype"];
+
+/// Replaces the metadata of a function or list, or removes it for `nil`.
+fn attach_metadata(value: Value, metadata: Option<Rc<Map>>) -> Result<Value, Error> {
+    match value {
+        Value::Function(function) => Ok(Value::Function(function.with_meta(metadata))),
+        Value::List(list) => Ok(Value::List(list.with_meta(metadata))),
+        _ => Err(Error::new(
+            "Only functions and lists can have metadata",
+            ErrorType::ArgumentError,
+        )),
+    }
+}
+
+/// `(with-meta value {:deprecated true})` returns the function or list with
+/// the metadata replaced. Metadata doesn't affect equality.
+fn with_meta(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [value, metadata] = <[Value; 2]>::try_from(arguments).map_err(|_| argument_error())?;
+
+    match metadata {
+        Value::Map(metadata) => attach_metadata(value, Some(Rc::new(metadata))),
+        Value::Nil => attach_metadata(value, None),
+        metadata => Err(type_error(Type::Map, &metadata)),
+    }
+}
+
+/// `(meta value)` returns the metadata map of a value, or nil.
+fn meta(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [value] => Ok(value
+            .meta()
+            .map_or(Value::Nil, |metadata| Value::Map((**metadata).clone()))),
+        _ => Err(argument_error()),
+    }
+}
+
+impl Interpreter {
+    /// `^{:private true} (defn helper ...)` attaches the metadata to the
+    /// definition, which keeps `:private` ones out of modules requiring this
+    /// one. Before other expressions it attaches to the value, like
+    /// `with-meta`.
+    pub(super) fn evaluate_metadata_expression(
+        &mut self,
+        metadata: &ASTNode,
+        expression: &ASTNode,
+    ) -> Result<Value, Error> {
+        let metadata = match self.evaluate(metadata)? {
+            Value::Map(metadata) => metadata,
+            metadata => return Err(type_error(Type::Map, &metadata)),
+        };
+
+        match expression {
+            ASTNode::FunctionDeclaration {
+                identifier,
+                docstring,
+                arities,
+            } => {
+                let function = self.evaluate_function_declaration(
+                    identifier,
+                    docstring,
+                    arities,
+                    metadata.clone(),
+                )?;
+                self.environment_stack
+                    .set_metadata(identifier, Rc::new(metadata));
+
+                return Ok(function);
+            }
+
+            ASTNode::CallExpression(callee, arguments, _) => match (&**callee, arguments.first()) {
+                (ASTNode::Identifier(form), Some(ASTNode::Identifier(name)))
+                    if DEFINING_FORMS.contains(&form.as_str()) =>
+                {
+                    let value = self.evaluate(expression)?;
+                    self.environment_stack.set_metadata(name, Rc::new(metadata));
+
+                    return Ok(value);
+                }
+                _ => attach_metadata(self.evaluate(expression)?, Some(Rc::new(metadata))),
+            },
+
+            _ => attach_metadata(self.evaluate(expression)?, Some(Rc::new(metadata))),
+        }
+    }
+
+    /// Warns about calls at `span` of functions marked `:deprecated`. A string
+    /// is shown as the reason, e.g. `{:deprecated "use area instead"}`.
+    pub(super) fn warn_if_deprecated(&mut self, function: &Value, span: Span) {
+        let (Value::Function(declared), Some(metadata)) = (function, function.meta()) else {
+            return;
+        };
+
+        let message = match metadata.get(&Value::Keyword(Keyword::new("deprecated"))) {
+            Some(Value::String(reason)) => {
+                format!("{} is deprecated: {}", declared.identifier(), reason)
+            }
+            Some(deprecated) if deprecated.is_truthy() => {
+                format!("{} is deprecated", declared.identifier())
+            }
+            _ => return,
+        };
+
+        self.warn(message, span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+    use crate::Warning;
+
+    #[test]
+    fn test_with_meta() {
+        assert_eq!(
+            display("(meta (with-meta (list 1 2) {:source \"input\"}))"),
+            "{:source input}"
+        );
+        assert_eq!(
+            display("(let [f (fn [x] x)] (list (meta f) (meta (with-meta f {:test true}))))"),
+            "(nil {:test true})"
+        );
+        assert_eq!(
+            display("(meta (with-meta (with-meta [1] {:a 1}) nil))"),
+            "nil"
+        );
+        assert_eq!(
+            display("(is-equal (with-meta (list 1 2) {:a 1}) (list 1 2))"),
+            "true"
+        );
+
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(with-meta 1 {:a 1})")
+                .unwrap_err()
+                .error_type(),
+            &ErrorType::ArgumentError
+        );
+    }
+
+    #[test]
+    fn test_metadata_syntax() {
+        assert_eq!(
+            display("(meta ^{:origin \"repl\"} [1 2])"),
+            "{:origin repl}"
+        );
+        assert_eq!(
+            display("^:private (defn helper \"Helps.\" [] 1) (meta helper)"),
+            "{:private true :doc Helps.}"
+        );
+        assert_eq!(display("(defn plain [] 1) (meta plain)"), "nil");
+    }
+
+    #[test]
+    fn test_deprecation_warnings() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+^{:deprecated "use area instead"} (defn size [w h] (multiply w h))
+(defn area [w h] (multiply w h))
+(map (fn [w] (size w 2)) (list 1 2))
+(area 1 2)
+"#;
+
+        evaluate_source(&mut interpreter, source).unwrap();
+
+        assert_eq!(
+            interpreter.take_warnings(),
+            vec![Warning {
+                message: "size is deprecated: use area instead".to_string(),
+                span: Span {
+                    line: 4,
+                    column: 14
+                },
+                file: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{environment::Scope, prelude::prelude_source, Interpreter};
+use crate::{parser::parse, tokenizer::tokenize_with_spans, ASTNode, Error, ErrorType, Value};
+
+/// The extension of risp source files, used for modules required by name.
+const MODULE_EXTENSION: &str = "rsp";
+
+/// A module that has been loaded, with the bindings of its top level.
+#[derive(Clone)]
+pub(super) struct Module {
+    /// The name declared with `ns`, if any.
+    namespace: Option<String>,
+    pub(super) environment: Scope,
+}
+
+/// A file that is being evaluated, used to resolve relative module paths
+/// and to detect modules requiring each other.
+pub(super) struct LoadingFile {
+    path: Rc<Path>,
+    namespace: Option<String>,
+}
+
+impl LoadingFile {
+    pub(super) fn new(path: &Path) -> Self {
+        Self {
+            path: Rc::from(path),
+            namespace: None,
+        }
+    }
+}
+
+/// Which bindings of a module `require` and `import` copy into the current
+/// environment, so they can be used without qualifying them.
+enum Refer {
+    None,
+    All { exclude: Vec<String> },
+    Only(Vec<String>),
+}
+
+struct RequireExpression {
+    path: PathBuf,
+    /// The alias used if neither `:as` nor `ns` give one.
+    default_alias: String,
+    alias: Option<String>,
+    refer: Refer,
+}
+
+/// Parses `"path/to/file.rsp"` or `my.module` followed by the `:as alias`,
+/// `:only [names]` and `:exclude [names]` options.
+fn parse_require_expression(
+    arguments: &[ASTNode],
+    refer_all: bool,
+) -> Result<RequireExpression, Error> {
+    let (path, default_alias) = match arguments.first() {
+        Some(ASTNode::StringLiteral(path)) => {
+            let path = PathBuf::from(path);
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            (path, stem)
+        }
+
+        Some(ASTNode::Identifier(name)) => {
+            let mut path: PathBuf = name.split('.').collect();
+            path.set_extension(MODULE_EXTENSION);
+
+            (path, name.to_string())
+        }
+
+        _ => {
+            return Err(Error::new(
+                "Expected a module path or name",
+                ErrorType::ArgumentError,
+            ))
+        }
+    };
+
+    let mut expression = RequireExpression {
+        path,
+        default_alias,
+        alias: None,
+        refer: if refer_all {
+            Refer::All {
+                exclude: Vec::new(),
+            }
+        } else {
+            Refer::None
+        },
+    };
+
+    for option in arguments[1..].chunks(2) {
+        match option {
+            [ASTNode::KeywordLiteral(keyword), ASTNode::Identifier(alias)] if keyword == "as" => {
+                expression.alias = Some(alias.to_string());
+            }
+
+            [ASTNode::KeywordLiteral(keyword), ASTNode::ListExpression(names)]
+                if keyword == "only" =>
+            {
+                expression.refer = Refer::Only(identifier_names(names)?);
+            }
+
+            [ASTNode::KeywordLiteral(keyword), ASTNode::ListExpression(names)]
+                if keyword == "exclude" =>
+            {
+                expression.refer = Refer::All {
+                    exclude: identifier_names(names)?,
+                };
+            }
+
+            _ => {
+                return Err(Error::new(
+                    "Expected :as, :only or :exclude",
+                    ErrorType::ArgumentError,
+                ))
+            }
+        }
+    }
+
+    return Ok(expression);
+}
+
+fn identifier_names(nodes: &[ASTNode]) -> Result<Vec<String>, Error> {
+    nodes
+        .iter()
+        .map(|node| match node {
+            ASTNode::Identifier(name) => Ok(name.to_string()),
+            _ => Err(Error::new("Expected a name", ErrorType::ArgumentError)),
+        })
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    return path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+}
+
+impl Interpreter {
+    /// Adds a directory to search for modules that aren't found next to the
+    /// file requiring them.
+    pub fn add_module_path(&mut self, path: impl Into<PathBuf>) {
+        self.module_search_path.push(path.into());
+    }
+
+    /// `(ns my.module)` names the module being loaded, which is the default
+    /// alias it is required as.
+    pub(super) fn evaluate_ns_expression(&mut self, arguments: &[ASTNode]) -> Result<Value, Error> {
+        match arguments {
+            [ASTNode::Identifier(name)] => {
+                if let Some(file) = self.loading.last_mut() {
+                    file.namespace = Some(name.to_string());
+                }
+
+                return Ok(Value::Nil);
+            }
+
+            _ => Err(Error::new(
+                "Expected a namespace name",
+                ErrorType::ArgumentError,
+            )),
+        }
+    }
+
+    /// `(require "path/to/file.rsp" :as m)` loads a module, unless it is
+    /// loaded already, and makes its bindings available as `m/name`.
+    /// `import` does the same and also makes them available unqualified.
+    pub(super) fn evaluate_require_expression(
+        &mut self,
+        arguments: &[ASTNode],
+        refer_all: bool,
+    ) -> Result<Value, Error> {
+        let expression = parse_require_expression(arguments, refer_all)?;
+        let path = self.resolve_module(&expression.path)?;
+        let module = self.load_module(path)?;
+
+        let alias = expression
+            .alias
+            .or(module.namespace)
+            .unwrap_or(expression.default_alias);
+
+        self.environment_stack
+            .set_alias(&alias, module.environment.clone());
+
+        let environment = module.environment.borrow();
+
+        let names = match expression.refer {
+            Refer::None => Vec::new(),
+            Refer::All { exclude } => environment
+                .names()
+                .into_iter()
+                .filter(|name| !exclude.contains(name) && !environment.is_private(name))
+                .collect(),
+            Refer::Only(names) => names,
+        };
+
+        for name in names.iter() {
+            let value = match environment.is_private(name) {
+                true => None,
+                false => environment.get(name),
+            };
+            let value = value.ok_or_else(|| {
+                Error::new(
+                    "Undefined variable",
+                    ErrorType::UndefinedVariable(format!("{}/{}", alias, name)),
+                )
+            })?;
+
+            self.environment_stack.set(name, value);
+        }
+
+        return Ok(Value::Nil);
+    }
+
+    /// Looks for a module in the prelude, next to the file being evaluated,
+    /// or the working directory, and then in the module search path.
+    fn resolve_module(&self, path: &Path) -> Result<PathBuf, Error> {
+        if self.prelude && prelude_source(path).is_some() {
+            return Ok(path.to_path_buf());
+        }
+
+        let directory = match self.loading.last() {
+            Some(file) => file
+                .path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            None => PathBuf::from("."),
+        };
+
+        return std::iter::once(directory)
+            .chain(self.module_search_path.iter().cloned())
+            .map(|directory| directory.join(path))
+            .find(|candidate| candidate.is_file())
+            .and_then(|candidate| candidate.canonicalize().ok())
+            .ok_or_else(|| {
+                Error::new(
+                    "Module not found",
+                    ErrorType::ModuleNotFound(path.display().to_string()),
+                )
+            });
+    }
+
+    pub(super) fn load_module(&mut self, path: PathBuf) -> Result<Module, Error> {
+        if let Some(position) = self.loading.iter().position(|file| *file.path == *path) {
+            let mut chain: Vec<String> = self.loading[position..]
+                .iter()
+                .map(|file| file_name(&file.path))
+                .collect();
+            chain.push(file_name(&path));
+
+            return Err(Error::new(
+                "Circular require",
+                ErrorType::ModuleCycle(chain.join(" -> ")),
+            ));
+        }
+
+        if let Some(module) = self.modules.get(&path) {
+            return Ok(module.clone());
+        }
+
+        let source = match prelude_source(&path) {
+            Some(source) => source.to_string(),
+            None => std::fs::read_to_string(&path).map_err(Error::from)?,
+        };
+        let tokens = tokenize_with_spans(&source);
+        let expressions = parse(&mut tokens.into())?;
+
+        let environment = self.environment_stack.new_module_environment();
+        let saved = self.environment_stack.enter_module(environment.clone());
+        let file = LoadingFile::new(&path);
+        let previous_file = self.current_file.replace(file.path.clone());
+        self.loading.push(file);
+
+        let result = expressions
+            .iter()
+            .try_for_each(|expression| self.evaluate(expression).map(|_| ()));
+
+        let file = self.loading.pop().unwrap();
+        self.current_file = previous_file;
+        self.environment_stack.leave_module(saved);
+
+        if let Err(error) = result {
+            // Functions declared by the module may have escaped, e.g. in a
+            // thrown value, and only hold on to its environment weakly.
+            self.failed_modules.push(environment);
+            return Err(error.in_file(&path));
+        }
+
+        let module = Module {
+            namespace: file.namespace,
+            environment,
+        };
+        self.modules.insert(path, module.clone());
+
+        return Ok(module);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::evaluate_source;
+
+    /// Writes `files` to a fresh directory and returns an interpreter that
+    /// searches it for modules.
+    fn interpreter_with_modules(name: &str, files: &[(&str, &str)]) -> Interpreter {
+        let directory = std::env::temp_dir().join(format!("risp-modules-{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        for (path, source) in files.iter() {
+            let path = directory.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+
+        let mut interpreter = Interpreter::new();
+        interpreter.add_module_path(directory);
+        return interpreter;
+    }
+
+    #[test]
+    fn test_qualified_access() {
+        let mut interpreter = interpreter_with_modules(
+            "qualified",
+            &[(
+                "geometry/shapes.rsp",
+                "(ns geometry.shapes)
+                 (define sides 4)
+                 (defn area [w h] (add w h))
+                 (defn square [w] (area w w))",
+            )],
+        );
+
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(require \"geometry/shapes.rsp\" :as s)
+                 (list (s/square 3) s/sides)"
+            )
+            .map(|value| value.to_display_string()),
+            Ok("(6 4)".to_string())
+        );
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(require geometry.shapes) (geometry.shapes/area 2 4)"
+            ),
+            Ok(Value::Number(6))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "area"),
+            Err(Error::new(
+                "Undefined variable",
+                ErrorType::UndefinedVariable("area".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn test_selective_imports() {
+        let mut interpreter = interpreter_with_modules(
+            "selective",
+            &[(
+                "numbers.rsp",
+                "(define one 1) (define two 2) (define three 3)",
+            )],
+        );
+
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(require numbers :only [one]) (list one numbers/two)"
+            )
+            .map(|value| value.to_display_string()),
+            Ok("(1 2)".to_string())
+        );
+        assert!(evaluate_source(&mut interpreter, "two").is_err());
+
+        let mut interpreter = interpreter_with_modules(
+            "selective-exclude",
+            &[(
+                "numbers.rsp",
+                "(define one 1) (define two 2) (define three 3)",
+            )],
+        );
+
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(import numbers :exclude [two]) (list one three)"
+            )
+            .map(|value| value.to_display_string()),
+            Ok("(1 3)".to_string())
+        );
+        assert!(evaluate_source(&mut interpreter, "two").is_err());
+    }
+
+    #[test]
+    fn test_private_definitions() {
+        let mut interpreter = interpreter_with_modules(
+            "private",
+            &[(
+                "counter.rsp",
+                "^:private (define start 10)
+                 ^{:private true} (defn step [n] (add n 1))
+                 (defn next-count [] (step start))",
+            )],
+        );
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(import counter) (next-count)"),
+            Ok(Value::Number(11))
+        );
+        assert!(evaluate_source(&mut interpreter, "step").is_err());
+        assert_eq!(
+            evaluate_source(&mut interpreter, "counter/start"),
+            Err(Error::new(
+                "Undefined variable",
+                ErrorType::UndefinedVariable("counter/start".to_string())
+            ))
+        );
+        assert!(evaluate_source(&mut interpreter, "(require counter :only [step])").is_err());
+    }
+
+    #[test]
+    fn test_modules_are_loaded_once() {
+        let mut interpreter = interpreter_with_modules(
+            "cached",
+            &[
+                ("counter.rsp", "(define count (atom 0))"),
+                ("a.rsp", "(require counter) (swap! counter/count add 1)"),
+                ("b.rsp", "(require counter) (swap! counter/count add 1)"),
+            ],
+        );
+
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(require a) (require b) (require a) (require counter) @counter/count"
+            ),
+            Ok(Value::Number(2))
+        );
+    }
+
+    #[test]
+    fn test_circular_requires() {
+        let mut interpreter = interpreter_with_modules(
+            "circular",
+            &[("a.rsp", "(require b)"), ("b.rsp", "(require \"a.rsp\")")],
+        );
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(require a)"),
+            Err(Error::new(
+                "Circular require",
+                ErrorType::ModuleCycle("a.rsp -> b.rsp -> a.rsp".to_string())
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(require c)"),
+            Err(Error::new(
+                "Module not found",
+                ErrorType::ModuleNotFound("c.rsp".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn test_backtraces_name_the_module() {
+        let mut interpreter =
+            interpreter_with_modules("backtraces", &[("fail.rsp", "(defn fail [] (car 1))")]);
+
+        let error = evaluate_source(&mut interpreter, "(require fail)\n(fail/fail)").unwrap_err();
+        let frames: Vec<_> = error
+            .backtrace()
+            .iter()
+            .map(|frame| {
+                let file = frame.file.as_ref().map(|file| file_name(file));
+                (frame.function.as_str(), file, frame.span.unwrap().line)
+            })
+            .collect();
+
+        assert_eq!(
+            frames,
+            vec![("car", Some("fail.rsp".to_string()), 1), ("fail", None, 2)]
+        );
+    }
+}
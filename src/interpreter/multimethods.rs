@@ -0,0 +1,302 @@
+use super::{builtins::argument_error, call::KeywordArguments, Interpreter};
+use crate::{
+    value::{BuiltinFunction, Dispatch, MultiMethod},
+    ASTNode, Error, ErrorType, FunctionArity, Value,
+};
+
+pub const MULTIMETHOD_BUILTINS: &[(&str, BuiltinFunction)] = &[("type-of", type_of)];
+
+/// `(type-of value)` returns the name of the type of the value as a string,
+/// e.g. `"Number"` or the name of a record.
+fn type_of(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [value] => Ok(Value::String(value.value_type().to_string())),
+        _ => Err(argument_error()),
+    }
+}
+
+/// The dispatch values a protocol method tries for a value, most specific
+/// first: the name of its type, then the name of its sum type for variants.
+fn type_dispatch_values(value: &Value) -> Vec<Value> {
+    let mut dispatch_values = vec![Value::String(value.value_type().to_string())];
+
+    if let Value::Record(record) = value {
+        if let Some(sum_type) = record.record_type().sum_type() {
+            dispatch_values.push(Value::String(sum_type.name().to_string()));
+        }
+    }
+
+    return dispatch_values;
+}
+
+impl Interpreter {
+    /// `(defmulti area shape-kind)` defines a multimethod that calls the
+    /// method for the value `shape-kind` returns for its arguments.
+    pub(super) fn evaluate_defmulti_expression(
+        &mut self,
+        arguments: &[ASTNode],
+    ) -> Result<Value, Error> {
+        let (name, dispatch_node) = match arguments {
+            [ASTNode::Identifier(name), dispatch_node] => (name, dispatch_node),
+            _ => {
+                return Err(Error::new(
+                    "Expected a name and a dispatch function",
+                    ErrorType::ArgumentError,
+                ))
+            }
+        };
+
+        let dispatch_function = self.evaluate(dispatch_node)?;
+        let multi_method = Value::MultiMethod(MultiMethod::new(
+            name,
+            Dispatch::Function(dispatch_function),
+        ));
+        self.environment_stack.set(name, multi_method.clone());
+
+        return Ok(multi_method);
+    }
+
+    /// `(defprotocol Shape (area [shape]) (perimeter [shape]))` defines a
+    /// multimethod dispatching on the type of its first argument for every
+    /// method, and binds `Shape` to the list of them.
+    pub(super) fn evaluate_defprotocol_expression(
+        &mut self,
+        arguments: &[ASTNode],
+    ) -> Result<Value, Error> {
+        let (name, signatures) = match arguments {
+            [ASTNode::Identifier(name), signatures @ ..] if !signatures.is_empty() => {
+                (name, signatures)
+            }
+            _ => {
+                return Err(Error::new(
+                    "Expected a protocol name and its methods",
+                    ErrorType::ArgumentError,
+                ))
+            }
+        };
+
+        let mut methods = Vec::with_capacity(signatures.len());
+
+        for signature in signatures.iter() {
+            let method_name = match signature {
+                ASTNode::CallExpression(callee, _, _) => match callee.as_ref() {
+                    ASTNode::Identifier(method_name) => method_name,
+                    _ => return Err(Error::new("Expected a method", ErrorType::ArgumentError)),
+                },
+                _ => return Err(Error::new("Expected a method", ErrorType::ArgumentError)),
+            };
+
+            let method = Value::MultiMethod(MultiMethod::new(method_name, Dispatch::Type));
+            self.environment_stack.set(method_name, method.clone());
+            methods.push(method);
+        }
+
+        let protocol = Value::List(methods.into());
+        self.environment_stack.set(name, protocol.clone());
+
+        return Ok(protocol);
+    }
+
+    /// Adds a method to a multimethod, or its default method when the
+    /// dispatch value is `:default`.
+    pub(super) fn evaluate_method_declaration(
+        &mut self,
+        identifier: &str,
+        dispatch_node: &ASTNode,
+        arities: &[FunctionArity],
+    ) -> Result<Value, Error> {
+        let multi_method = match self.environment_stack.get(identifier) {
+            Some(Value::MultiMethod(multi_method)) => multi_method,
+            Some(_) => {
+                return Err(Error::new(
+                    "Expected a multimethod",
+                    ErrorType::ArgumentError,
+                ))
+            }
+            None => return Err(self.undefined_variable(identifier)),
+        };
+
+        let method = Value::Function(self.declare_function(identifier, arities));
+
+        match dispatch_node {
+            ASTNode::KeywordLiteral(keyword) if keyword == "default" => {
+                multi_method.set_default_method(method)
+            }
+            dispatch_node => {
+                let dispatch_value = self.evaluate(dispatch_node)?;
+                multi_method.add_method(dispatch_value, method);
+            }
+        }
+
+        return Ok(Value::MultiMethod(multi_method));
+    }
+
+    /// Implements the methods of a protocol for the type named `type_name`.
+    pub(super) fn evaluate_type_extension(
+        &mut self,
+        type_name: &str,
+        protocol: &str,
+        methods: &[(String, Vec<FunctionArity>)],
+    ) -> Result<Value, Error> {
+        let protocol_methods = match self.environment_stack.get(protocol) {
+            Some(Value::List(protocol_methods)) => protocol_methods,
+            Some(_) => {
+                return Err(Error::new(
+                    &format!("{} is not a protocol", protocol),
+                    ErrorType::ArgumentError,
+                ))
+            }
+            None => return Err(self.undefined_variable(protocol)),
+        };
+
+        for (method_name, arities) in methods.iter() {
+            let multi_method = protocol_methods
+                .iter()
+                .find_map(|method| match method {
+                    Value::MultiMethod(method) if method.name() == method_name => Some(method),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    Error::new(
+                        &format!("{} is not a method of {}", method_name, protocol),
+                        ErrorType::ArgumentError,
+                    )
+                })?;
+
+            multi_method.add_method(
+                Value::String(type_name.to_string()),
+                Value::Function(self.declare_function(method_name, arities)),
+            );
+        }
+
+        return Ok(Value::Nil);
+    }
+
+    /// Calls the method of a multimethod for the dispatch value of the
+    /// arguments, or its default method if it has none for it.
+    pub(super) fn call_multi_method(
+        &mut self,
+        multi_method: &MultiMethod,
+        arguments: Vec<Value>,
+        keyword_arguments: KeywordArguments,
+    ) -> Result<Value, Error> {
+        let dispatch_values = match multi_method.dispatch() {
+            Dispatch::Function(function) => vec![self.call_value(function, arguments.clone())?],
+            Dispatch::Type => match arguments.first() {
+                Some(value) => type_dispatch_values(value),
+                None => return Err(argument_error()),
+            },
+        };
+
+        let method = dispatch_values
+            .iter()
+            .find_map(|dispatch_value| multi_method.method(dispatch_value))
+            .or_else(|| multi_method.default_method());
+
+        match method {
+            Some(method) => {
+                self.call_value_with_keyword_arguments(&method, arguments, keyword_arguments)
+            }
+            None => Err(Error::new(
+                "No method",
+                ErrorType::NoMethod {
+                    function: multi_method.name().to_string(),
+                    dispatch_value: dispatch_values[0].clone(),
+                },
+            )
+            .with_frame(multi_method.name(), String::new())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_multimethods() {
+        assert_eq!(
+            display(
+                "(defmulti describe (fn [animal] (get animal \"kind\")))
+                 (defmethod describe \"dog\" [animal] \"woof\")
+                 (defmethod describe \"cat\" [animal] \"meow\")
+                 (defmethod describe :default [animal] \"...\")
+                 (map describe (list {\"kind\" \"dog\"} {\"kind\" \"cat\"} {\"kind\" \"fish\"}))"
+            ),
+            "(woof meow ...)"
+        );
+    }
+
+    #[test]
+    fn test_protocols() {
+        assert_eq!(
+            display(
+                "(defprotocol Describe (describe [value]) (size [value]))
+                 (defrecord Point [x y])
+                 (deftype Shape (Circle r) Empty)
+                 (extend-type String Describe
+                   (describe [s] (str \"string \" s))
+                   (size [s] 1))
+                 (extend-type List Describe
+                   (describe [l] (str \"list of \" (size l))))
+                 (extend-type Point Describe
+                   (describe [p] (str \"point \" (point-x p))))
+                 (extend-type Shape Describe
+                   (describe [s] \"shape\"))
+                 (extend-type Empty Describe
+                   (describe [s] \"empty\"))
+                 (defmethod size :default [value] 0)
+                 (list (describe \"a\") (describe (list 1 2)) (describe (Point 1 2))
+                       (describe (Circle 1)) (describe Empty) (size 5))"
+            ),
+            "(string a list of 0 point 1 shape empty 0)"
+        );
+    }
+
+    #[test]
+    fn test_type_of() {
+        assert_eq!(
+            display(
+                "(defrecord Point [x y]) (list (type-of 1) (type-of \"a\") (type-of (Point 1 2)))"
+            ),
+            "(Number String Point)"
+        );
+    }
+
+    #[test]
+    fn test_no_method() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(
+            &mut interpreter,
+            "(defprotocol Shape (area [shape]))
+             (defmulti speak identity)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(area 1)")
+                .unwrap_err()
+                .to_string(),
+            "no method: area has no method for Number in call to area"
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(speak \"moo\")")
+                .unwrap_err()
+                .error_type(),
+            &ErrorType::NoMethod {
+                function: "speak".to_string(),
+                dispatch_value: Value::String("moo".to_string()),
+            }
+        );
+        assert_eq!(
+            evaluate_source(
+                &mut interpreter,
+                "(extend-type Number Shape (volume [n] n))"
+            )
+            .unwrap_err()
+            .to_string(),
+            "argument error: volume is not a method of Shape"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::{parser::Pattern, value::Keyword, Error, ErrorType, Type, Value};
+
+/// Destructures `value` according to `pattern`, collecting the resulting
+/// names in `bindings`.
+pub fn bind_pattern(
+    pattern: &Pattern,
+    value: Value,
+    bindings: &mut HashMap<String, Value>,
+) -> Result<(), Error> {
+    match pattern {
+        Pattern::Identifier(name) => {
+            bindings.insert(name.to_string(), value);
+        }
+
+        Pattern::Sequence { elements, rest } => {
+            let values = match value {
+                Value::List(values) => values,
+
+                _ => {
+                    return Err(Error::new(
+                        "Type error",
+                        ErrorType::TypeError {
+                            expected_type: Type::List,
+                            actual_type: value.value_type(),
+                        },
+                    ))
+                }
+            };
+
+            if values.len() < elements.len() || (rest.is_none() && values.len() > elements.len()) {
+                return Err(Error::new(
+                    "Wrong number of elements to destructure",
+                    ErrorType::DestructuringError {
+                        expected_length: elements.len(),
+                        actual_length: values.len(),
+                    },
+                ));
+            }
+
+            let mut values = values.into_iter();
+
+            for element in elements.iter() {
+                bind_pattern(element, values.next().unwrap(), bindings)?;
+            }
+
+            if let Some(rest) = rest {
+                bind_pattern(rest, Value::List(values.collect()), bindings)?;
+            }
+        }
+
+        Pattern::Map { keys } => {
+            let map = match value {
+                Value::Map(map) => map,
+
+                _ => {
+                    return Err(Error::new(
+                        "Type error",
+                        ErrorType::TypeError {
+                            expected_type: Type::Map,
+                            actual_type: value.value_type(),
+                        },
+                    ))
+                }
+            };
+
+            // Keyword keys take precedence over string keys of the same name.
+            for key in keys.iter() {
+                let value = map
+                    .get(&Value::Keyword(Keyword::new(key)))
+                    .or_else(|| map.get(&Value::String(key.to_string())))
+                    .cloned()
+                    .unwrap_or(Value::Nil);
+
+                bindings.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Map;
+
+    fn sequence(elements: Vec<Pattern>, rest: Option<Pattern>) -> Pattern {
+        Pattern::Sequence {
+            elements,
+            rest: rest.map(Box::new),
+        }
+    }
+
+    fn identifier(name: &str) -> Pattern {
+        Pattern::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn test_binding_nested_sequences() {
+        let pattern = sequence(
+            vec![sequence(vec![identifier("a"), identifier("b")], None)],
+            Some(identifier("rest")),
+        );
+        let value = Value::List(
+            vec![
+                Value::List(vec![Value::Number(1), Value::Number(2)].into()),
+                Value::Number(3),
+            ]
+            .into(),
+        );
+
+        let mut bindings = HashMap::new();
+        assert_eq!(bind_pattern(&pattern, value, &mut bindings), Ok(()));
+
+        assert_eq!(bindings.get("a"), Some(&Value::Number(1)));
+        assert_eq!(bindings.get("b"), Some(&Value::Number(2)));
+        assert_eq!(
+            bindings.get("rest"),
+            Some(&Value::List(vec![Value::Number(3)].into()))
+        );
+    }
+
+    #[test]
+    fn test_binding_map_keys() {
+        let pattern = Pattern::Map {
+            keys: vec!["x".to_string(), "z".to_string()],
+        };
+        let mut map = Map::new();
+        map.insert(Value::String("x".to_string()), Value::Number(1));
+
+        let mut bindings = HashMap::new();
+        assert_eq!(
+            bind_pattern(&pattern, Value::Map(map), &mut bindings),
+            Ok(())
+        );
+
+        assert_eq!(bindings.get("x"), Some(&Value::Number(1)));
+        assert_eq!(bindings.get("z"), Some(&Value::Nil));
+    }
+
+    #[test]
+    fn test_binding_keyword_keys() {
+        let pattern = Pattern::Map {
+            keys: vec!["x".to_string(), "y".to_string()],
+        };
+        let mut map = Map::new();
+        map.insert(Value::String("x".to_string()), Value::Number(1));
+        map.insert(Value::Keyword(Keyword::new("x")), Value::Number(2));
+        map.insert(Value::Keyword(Keyword::new("y")), Value::Number(3));
+
+        let mut bindings = HashMap::new();
+        assert_eq!(
+            bind_pattern(&pattern, Value::Map(map), &mut bindings),
+            Ok(())
+        );
+
+        assert_eq!(bindings.get("x"), Some(&Value::Number(2)));
+        assert_eq!(bindings.get("y"), Some(&Value::Number(3)));
+    }
+
+    #[test]
+    fn test_binding_mismatched_shapes() {
+        let pattern = sequence(vec![identifier("a"), identifier("b")], None);
+
+        assert_eq!(
+            bind_pattern(
+                &pattern,
+                Value::List(vec![Value::Number(1)].into()),
+                &mut HashMap::new()
+            ),
+            Err(Error::new(
+                "Wrong number of elements to destructure",
+                ErrorType::DestructuringError {
+                    expected_length: 2,
+                    actual_length: 1,
+                },
+            ))
+        );
+
+        assert_eq!(
+            bind_pattern(&pattern, Value::Number(1), &mut HashMap::new()),
+            Err(Error::new(
+                "Type error",
+                ErrorType::TypeError {
+                    expected_type: Type::List,
+                    actual_type: Type::Number,
+                },
+            ))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use super::Interpreter;
+
+/// The standard library written in risp, by the path `require` resolves
+/// each module to, e.g. `risp.list` to `risp/list.rsp`.
+const PRELUDE_MODULES: &[(&str, &str)] = &[
+    ("risp/core.rsp", include_str!("../prelude/core.rsp")),
+    ("risp/list.rsp", include_str!("../prelude/list.rsp")),
+    ("risp/math.rsp", include_str!("../prelude/math.rsp")),
+    ("risp/string.rsp", include_str!("../prelude/string.rsp")),
+];
+
+/// The module every interpreter but a bare one imports.
+const CORE_MODULE: &str = "risp/core.rsp";
+
+/// The source of a prelude module, if `path` names one.
+pub(super) fn prelude_source(path: &Path) -> Option<&'static str> {
+    return PRELUDE_MODULES
+        .iter()
+        .find(|(module_path, _)| path == Path::new(module_path))
+        .map(|(_, source)| *source);
+}
+
+impl Interpreter {
+    /// Makes the prelude modules available to `require` and defines the
+    /// core module next to the builtins, so every module sees it.
+    pub(super) fn load_prelude(&mut self) {
+        self.prelude = true;
+
+        let core = self
+            .load_module(PathBuf::from(CORE_MODULE))
+            .expect("the prelude is valid");
+
+        let environment = core.environment.borrow();
+
+        for name in environment.names() {
+            self.environment_stack
+                .define_builtin(&name, environment.get(&name).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+    use crate::{Error, ErrorType};
+
+    #[test]
+    fn test_core_is_always_available() {
+        assert_eq!(
+            display("(list (inc 1) (not nil) ((partial add 1) 2) ((comp inc inc) 0))"),
+            "(2 true 3 2)"
+        );
+        assert_eq!(
+            display(
+                "(define counter (atom 0))
+                 (defn bump [] (swap! counter inc))
+                 (bump)
+                 @counter"
+            ),
+            "1"
+        );
+    }
+
+    #[test]
+    fn test_list_module() {
+        assert_eq!(
+            display(
+                "(require risp.list :as l)
+                 (list (l/last (list 1 2 3))
+                       (l/index-of (list 1 2 3) 3)
+                       (l/take-while (fn [x] (is-less x 3)) (list 1 2 3 1))
+                       (l/drop-while (fn [x] (is-less x 3)) (list 1 2 3 1))
+                       (l/partition 2 (list 1 2 3))
+                       (l/distinct (list 1 2 1 3)))"
+            ),
+            "(3 2 (1 2) (3 1) ((1 2) (3)) (1 2 3))"
+        );
+    }
+
+    #[test]
+    fn test_math_module() {
+        assert_eq!(
+            display(
+                "(import risp.math :only [pow gcd min sum is-odd])
+                 (list (pow 2 10) (gcd 12 -18) (min 3 1 2) (sum (range 5)) (is-odd 3))"
+            ),
+            "(1024 6 1 10 true)"
+        );
+    }
+
+    #[test]
+    fn test_overflowing_numbers() {
+        for source in [
+            "(inc 9223372036854775807)",
+            "(dec (subtract 0 9223372036854775807 1))",
+            "(import risp.math :only [abs]) (abs (subtract 0 9223372036854775807 1))",
+            "(import risp.math :only [sum]) (sum (list 9223372036854775807 1))",
+        ] {
+            let error = evaluate_source(&mut Interpreter::new(), source).unwrap_err();
+
+            assert_eq!(error.error_type(), &ErrorType::ArgumentError);
+            assert_eq!(error.message(), "argument error: integer overflow");
+        }
+    }
+
+    #[test]
+    fn test_string_module() {
+        assert_eq!(
+            display(
+                "(require risp.string :as s)
+                 (list (s/join \", \" (s/split \"a b c\" \" \"))
+                       (s/starts-with \"risp\" \"ri\")
+                       (s/ends-with \"risp\" \"ri\")
+                       (s/includes \"prelude\" \"lud\")
+                       (s/pad-left \"7\" 3 \"0\"))"
+            ),
+            "(a, b, c true false true 007)"
+        );
+    }
+
+    #[test]
+    fn test_bare_interpreters_have_no_prelude() {
+        let mut interpreter = Interpreter::bare();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(inc 1)"),
+            Err(Error::new(
+                "Undefined",
+                ErrorType::UndefinedFunction("inc".to_string())
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(require risp.list)"),
+            Err(Error::new(
+                "Module not found",
+                ErrorType::ModuleNotFound("risp/list.rsp".to_string())
+            ))
+        );
+    }
+}
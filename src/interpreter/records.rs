@@ -0,0 +1,307 @@
+use std::rc::Rc;
+
+use super::{builtins::type_error, Interpreter};
+use crate::{
+    value::{Builtin, Record, RecordType, SumType},
+    ASTNode, Error, ErrorType, Type, Value,
+};
+
+/// Turns a record name into the prefix of its accessors and predicate, e.g.
+/// `LineSegment` into `line-segment`.
+fn kebab_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous: Option<char> = None;
+
+    for character in name.chars() {
+        if character.is_uppercase()
+            && previous.is_some_and(|previous| previous.is_lowercase() || previous.is_numeric())
+        {
+            result.push('-');
+        }
+
+        result.extend(character.to_lowercase());
+        previous = Some(character);
+    }
+
+    return result;
+}
+
+fn constructor(record_type: Rc<RecordType>) -> Value {
+    let name = record_type.name().to_string();
+
+    return Value::Builtin(Builtin::from_closure(&name, move |_, arguments| {
+        let expected = record_type.field_names().len();
+
+        if arguments.len() < expected {
+            return Err(Error::new(
+                "Too few arguments",
+                ErrorType::TooFewArguments {
+                    expected,
+                    given: arguments.len(),
+                },
+            ));
+        } else if arguments.len() > expected {
+            return Err(Error::new(
+                "Too many arguments",
+                ErrorType::TooManyArguments {
+                    expected,
+                    given: arguments.len(),
+                },
+            ));
+        }
+
+        return Ok(Value::Record(Record::new(record_type.clone(), arguments)));
+    }));
+}
+
+/// A predicate that is true for records whose type passes `test`.
+fn predicate(name: &str, test: impl Fn(&RecordType) -> bool + 'static) -> Value {
+    return Value::Builtin(Builtin::from_closure(
+        name,
+        move |_, arguments| match &arguments[..] {
+            [Value::Record(record)] => Ok(Value::Boolean(test(record.record_type()))),
+            [_] => Ok(Value::Boolean(false)),
+            _ => Err(Error::new(
+                "Wrong number of arguments",
+                ErrorType::ArgumentError,
+            )),
+        },
+    ));
+}
+
+fn accessor(name: &str, record_type: Rc<RecordType>, field: String) -> Value {
+    return Value::Builtin(Builtin::from_closure(
+        name,
+        move |_, arguments| match &arguments[..] {
+            [Value::Record(record)] if *record.record_type() == record_type => {
+                Ok(record.get(&field).cloned().unwrap_or(Value::Nil))
+            }
+            [value] => Err(type_error(
+                Type::Record(record_type.name().to_string()),
+                value,
+            )),
+            _ => Err(Error::new(
+                "Wrong number of arguments",
+                ErrorType::ArgumentError,
+            )),
+        },
+    ));
+}
+
+/// Checks that every node is a distinct name, e.g. the fields of a record.
+fn distinct_names(nodes: &[ASTNode], message: &str) -> Result<Vec<String>, Error> {
+    let mut names = Vec::with_capacity(nodes.len());
+
+    for node in nodes.iter() {
+        match node {
+            ASTNode::Identifier(name) if !names.contains(name) => names.push(name.to_string()),
+            _ => return Err(Error::new(message, ErrorType::ArgumentError)),
+        }
+    }
+
+    return Ok(names);
+}
+
+impl Interpreter {
+    /// `(defrecord Point [x y])` defines the constructor `Point`, the
+    /// accessors `point-x` and `point-y` and the predicate `point?`.
+    pub(super) fn evaluate_defrecord_expression(
+        &mut self,
+        arguments: &[ASTNode],
+    ) -> Result<Value, Error> {
+        let (name, field_nodes) = match arguments {
+            [ASTNode::Identifier(name), ASTNode::ListExpression(fields)] => (name, fields),
+            _ => {
+                return Err(Error::new(
+                    "Expected a record name and a list of fields",
+                    ErrorType::ArgumentError,
+                ))
+            }
+        };
+
+        let fields = distinct_names(field_nodes, "Expected distinct field names")?;
+        let record_type = Rc::new(RecordType::new(name.to_string(), fields));
+        self.define_record_functions(&record_type);
+
+        let constructor = constructor(record_type);
+        self.environment_stack.set(name, constructor.clone());
+
+        return Ok(constructor);
+    }
+
+    /// `(deftype Shape (Circle r) (Rect w h) Empty)` defines every variant
+    /// like `defrecord` would, except that variants without parentheses are
+    /// values rather than constructors, and the predicate `shape?`.
+    pub(super) fn evaluate_deftype_expression(
+        &mut self,
+        arguments: &[ASTNode],
+    ) -> Result<Value, Error> {
+        let (name, variant_nodes) = match arguments {
+            [ASTNode::Identifier(name), variants @ ..] if !variants.is_empty() => (name, variants),
+            _ => {
+                return Err(Error::new(
+                    "Expected a type name and its variants",
+                    ErrorType::ArgumentError,
+                ))
+            }
+        };
+
+        // The name and fields of every variant, with `None` as the fields of
+        // variants written without parentheses.
+        let mut variants = Vec::with_capacity(variant_nodes.len());
+
+        for node in variant_nodes.iter() {
+            let variant = match node {
+                ASTNode::Identifier(variant) => (variant, None),
+                ASTNode::CallExpression(variant, fields, _) => match variant.as_ref() {
+                    ASTNode::Identifier(variant) => (
+                        variant,
+                        Some(distinct_names(fields, "Expected distinct field names")?),
+                    ),
+                    _ => return Err(Error::new("Expected a variant", ErrorType::ArgumentError)),
+                },
+                _ => return Err(Error::new("Expected a variant", ErrorType::ArgumentError)),
+            };
+
+            if variants.iter().any(|(name, _)| *name == variant.0) {
+                return Err(Error::new(
+                    "Expected distinct variant names",
+                    ErrorType::ArgumentError,
+                ));
+            }
+
+            variants.push(variant);
+        }
+
+        let sum_type = Rc::new(SumType::new(
+            name.to_string(),
+            variants.iter().map(|(name, _)| name.to_string()).collect(),
+        ));
+
+        for (variant, fields) in variants.into_iter() {
+            let record_type = Rc::new(RecordType::variant(
+                variant.to_string(),
+                fields.clone().unwrap_or_default(),
+                sum_type.clone(),
+            ));
+
+            self.define_record_functions(&record_type);
+
+            let value = match fields {
+                Some(_) => constructor(record_type),
+                None => Value::Record(Record::new(record_type, Vec::new())),
+            };
+            self.environment_stack.set(variant, value);
+        }
+
+        let predicate_name = format!("{}?", kebab_case(name));
+        let type_predicate = predicate(&predicate_name, move |record_type| {
+            record_type.sum_type() == Some(&*sum_type)
+        });
+        self.environment_stack.set(&predicate_name, type_predicate);
+
+        return Ok(Value::Nil);
+    }
+
+    /// Defines the predicate and accessors of a record type.
+    fn define_record_functions(&mut self, record_type: &Rc<RecordType>) {
+        let prefix = kebab_case(record_type.name());
+
+        let predicate_name = format!("{}?", prefix);
+        let predicate_type = record_type.clone();
+        self.environment_stack.set(
+            &predicate_name,
+            predicate(&predicate_name, move |other| *other == *predicate_type),
+        );
+
+        for field in record_type.field_names().iter() {
+            let accessor_name = format!("{}-{}", prefix, field);
+            self.environment_stack.set(
+                &accessor_name,
+                accessor(&accessor_name, record_type.clone(), field.to_string()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_kebab_case() {
+        assert_eq!(kebab_case("Point"), "point");
+        assert_eq!(kebab_case("LineSegment"), "line-segment");
+        assert_eq!(kebab_case("Vec3D"), "vec3-d");
+    }
+
+    #[test]
+    fn test_records() {
+        assert_eq!(
+            display("(defrecord Point [x y]) (Point 1 (list 2))"),
+            "#Point{:x 1 :y (2)}"
+        );
+        assert_eq!(
+            display(
+                "(defrecord LineSegment [start end])
+                 (define segment (LineSegment 1 5))
+                 (list (line-segment-end segment)
+                       (line-segment? segment)
+                       (line-segment? (list 1 5)))"
+            ),
+            "(5 true false)"
+        );
+    }
+
+    #[test]
+    fn test_record_equality() {
+        assert_eq!(
+            display(
+                "(defrecord Point [x y])
+                 (defrecord Size [x y])
+                 (list (is-equal (Point 1 2) (Point 1 2))
+                       (is-equal (Point 1 2) (Point 2 1))
+                       (is-equal (Point 1 2) (Size 1 2)))"
+            ),
+            "(true false false)"
+        );
+    }
+
+    #[test]
+    fn test_record_errors() {
+        let mut interpreter = Interpreter::new();
+        evaluate_source(
+            &mut interpreter,
+            "(defrecord Point [x y]) (defrecord Size [x y])",
+        )
+        .unwrap();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(point-x (Size 1 2))"),
+            Err(Error::new(
+                "Type error",
+                ErrorType::TypeError {
+                    expected_type: Type::Record("Point".to_string()),
+                    actual_type: Type::Record("Size".to_string()),
+                }
+            ))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(point-x 1)")
+                .unwrap_err()
+                .to_string(),
+            "type error: expected Point, got Number in call to point-x"
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(Point 1)"),
+            Err(Error::new(
+                "Too few arguments",
+                ErrorType::TooFewArguments {
+                    expected: 2,
+                    given: 1
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use super::{
+    builtins::{argument_error, parse_number, read_file, type_error},
+    exceptions::error_value,
+    Interpreter,
+};
+use crate::{value::BuiltinFunction, Error, ErrorType, Type, Value};
+
+pub const RESULT_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("ok", ok),
+    ("err", err),
+    ("is-ok", is_ok),
+    ("is-err", is_err),
+    ("unwrap", unwrap),
+    ("unwrap-or", unwrap_or),
+    ("unwrap-err", unwrap_err),
+    ("map-ok", map_ok),
+    ("map-err", map_err),
+    ("attempt", attempt),
+    ("try-read-file", try_read_file),
+    ("try-parse-number", try_parse_number),
+];
+
+/// Turns the outcome of a builtin into a result value, with errors as the
+/// same values `catch` would see.
+fn to_result(result: Result<Value, Error>) -> Value {
+    match result {
+        Ok(value) => Value::Ok(Box::new(value)),
+        Err(error) => Value::Err(Box::new(error_value(&error))),
+    }
+}
+
+fn ok(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [value]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+
+    return Ok(Value::Ok(Box::new(value)));
+}
+
+fn err(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [value]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+
+    return Ok(Value::Err(Box::new(value)));
+}
+
+fn is_ok(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [value] => Ok(Value::Boolean(matches!(value, Value::Ok(_)))),
+        _ => Err(argument_error()),
+    }
+}
+
+fn is_err(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [value] => Ok(Value::Boolean(matches!(value, Value::Err(_)))),
+        _ => Err(argument_error()),
+    }
+}
+
+/// `(unwrap result)` returns the value of an `ok` and throws the value of
+/// an `err`.
+fn unwrap(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 1]>::try_from(arguments) {
+        Ok([Value::Ok(value)]) => Ok(*value),
+        Ok([Value::Err(value)]) => Err(Error::new("Thrown", ErrorType::Thrown(*value))),
+        Ok([value]) => Err(type_error(Type::Result, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(unwrap-or result default)` returns the value of an `ok`, or `default`.
+fn unwrap_or(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([Value::Ok(value), _]) => Ok(*value),
+        Ok([Value::Err(_), default]) => Ok(default),
+        Ok([value, _]) => Err(type_error(Type::Result, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(unwrap-err result)` returns the value of an `err` and throws otherwise.
+fn unwrap_err(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 1]>::try_from(arguments) {
+        Ok([Value::Err(value)]) => Ok(*value),
+        Ok([Value::Ok(value)]) => Err(Error::new("Thrown", ErrorType::Thrown(*value))),
+        Ok([value]) => Err(type_error(Type::Result, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(map-ok f result)` applies `f` to the value of an `ok` and passes an
+/// `err` through unchanged.
+fn map_ok(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([function, Value::Ok(value)]) => Ok(Value::Ok(Box::new(
+            interpreter.call_value(&function, vec![*value])?,
+        ))),
+        Ok([_, error @ Value::Err(_)]) => Ok(error),
+        Ok([_, value]) => Err(type_error(Type::Result, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(map-err f result)` applies `f` to the value of an `err` and passes an
+/// `ok` through unchanged.
+fn map_err(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([function, Value::Err(value)]) => Ok(Value::Err(Box::new(
+            interpreter.call_value(&function, vec![*value])?,
+        ))),
+        Ok([_, ok @ Value::Ok(_)]) => Ok(ok),
+        Ok([_, value]) => Err(type_error(Type::Result, &value)),
+        Err(_) => Err(argument_error()),
+    }
+}
+
+/// `(attempt f args...)` calls `f` and returns its value as an `ok`, or
+/// whatever it raised as an `err`.
+fn attempt(interpreter: &mut Interpreter, mut arguments: Vec<Value>) -> Result<Value, Error> {
+    if arguments.is_empty() {
+        return Err(argument_error());
+    }
+
+    let function = arguments.remove(0);
+
+    return Ok(to_result(interpreter.call_value(&function, arguments)));
+}
+
+fn try_read_file(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    return Ok(to_result(read_file(interpreter, arguments)));
+}
+
+fn try_parse_number(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    return Ok(to_result(parse_number(interpreter, arguments)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_result_values() {
+        assert_eq!(
+            display("(list (is-ok (ok 1)) (is-err (ok 1)) (is-err (err 1)))"),
+            "(true false true)"
+        );
+        assert_eq!(display("(unwrap (ok 1))"), "1");
+        assert_eq!(display("(unwrap-or (err 1) 2)"), "2");
+        assert_eq!(display("(unwrap-err (err 1))"), "1");
+        assert_eq!(display("(map-ok (fn [a] (add a 1)) (ok 1))"), "#<Ok:2>");
+        assert_eq!(display("(map-ok (fn [a] (add a 1)) (err 1))"), "#<Err:1>");
+        assert_eq!(display("(map-err (fn [a] (add a 1)) (err 1))"), "#<Err:2>");
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(unwrap (err \"bad\"))"),
+            Err(Error::new(
+                "Thrown",
+                ErrorType::Thrown(Value::String("bad".to_string()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_non_throwing_builtins() {
+        assert_eq!(display("(try-parse-number \"42\")"), "#<Ok:42>");
+        assert_eq!(
+            display("(try-parse-number \"4x2\")"),
+            "#<Err:{:kind invalid-number :message invalid number: \"4x2\" is not a number}>"
+        );
+        assert_eq!(
+            display("(get (unwrap-err (try-read-file \"/does/not/exist\")) :kind)"),
+            "io-error"
+        );
+        assert_eq!(display("(attempt (fn [a] (throw a)) 5)"), "#<Err:5>");
+    }
+
+    #[test]
+    fn test_try_let_short_circuits() {
+        let mut interpreter = Interpreter::new();
+
+        evaluate_source(
+            &mut interpreter,
+            "(define calls (atom 0))
+             (defn parse-sum [a b]
+               (try? [x (try-parse-number a)
+                      _ (ok (swap! calls add 1))
+                      y (try-parse-number b)]
+                 (ok (add x y))))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(parse-sum \"1\" \"2\")")
+                .map(|value| value.to_display_string()),
+            Ok("#<Ok:3>".to_string())
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(is-err (parse-sum \"a\" \"2\"))"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "@calls"),
+            Ok(Value::Number(1))
+        );
+        assert_eq!(
+            evaluate_source(&mut interpreter, "(try? [a 1] a)"),
+            Err(type_error(Type::Result, &Value::Number(1)))
+        );
+    }
+}
@@ -0,0 +1,576 @@
+use super::{
+    builtins::{argument_error, type_error},
+    lazy::{lazy_concat, lazy_drop, lazy_filter, lazy_map, lazy_range, lazy_take, lazy_zip},
+    Interpreter,
+};
+use crate::{value::BuiltinFunction, Error, ErrorType, Type, Value};
+
+/// The sequence library. Every function here iterates natively instead of
+/// recursing through the evaluator, so it works on arbitrarily long lists.
+pub const SEQUENCE_BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("map", map),
+    ("filter", filter),
+    ("reduce", reduce),
+    ("range", range),
+    ("take", take),
+    ("drop", drop),
+    ("reverse", reverse),
+    ("nth", nth),
+    ("length", length),
+    ("concat", concat),
+    ("sort", sort),
+    ("zip", zip),
+    ("flatten", flatten),
+];
+
+/// Turns any collection into the list of values it contains. Maps become a
+/// list of `(key value)` pairs and strings a list of one character strings.
+/// Lazy sequences are realized completely.
+pub fn sequence_values(interpreter: &mut Interpreter, value: Value) -> Result<Vec<Value>, Error> {
+    match value {
+        Value::List(values) => Ok(values.into()),
+
+        Value::LazySequence(_) => {
+            let mut values = Vec::new();
+            let mut sequence = value;
+
+            while let Some((first, rest)) = interpreter.sequence_step(sequence)? {
+                values.push(first);
+                sequence = rest;
+            }
+
+            Ok(values)
+        }
+
+        Value::Map(map) => Ok(map
+            .iter()
+            .map(|(key, value)| Value::List(vec![key.clone(), value.clone()].into()))
+            .collect()),
+
+        Value::String(string) => Ok(string
+            .chars()
+            .map(|character| Value::String(character.to_string()))
+            .collect()),
+
+        Value::Nil => Ok(Vec::new()),
+
+        value => Err(type_error(Type::List, &value)),
+    }
+}
+
+fn is_lazy(value: &Value) -> bool {
+    matches!(value, Value::LazySequence(_))
+}
+
+/// Turns the collections that `sequence_step` can't step through, like
+/// strings and maps, into lists of their elements.
+fn steppable(interpreter: &mut Interpreter, collections: Vec<Value>) -> Result<Vec<Value>, Error> {
+    collections
+        .into_iter()
+        .map(|collection| match collection {
+            Value::List(_) | Value::LazySequence(_) | Value::Nil => Ok(collection),
+            collection => Ok(Value::List(
+                sequence_values(interpreter, collection)?.into(),
+            )),
+        })
+        .collect()
+}
+
+fn number_argument(value: &Value) -> Result<i64, Error> {
+    match value {
+        Value::Number(number) => Ok(*number),
+        value => Err(type_error(Type::Number, value)),
+    }
+}
+
+/// `(map f coll ...)` calls `f` with one element from every collection at a
+/// time, stopping at the end of the shortest one.
+fn map(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let mut arguments = arguments.into_iter();
+    let function = arguments.next().ok_or_else(argument_error)?;
+
+    let arguments: Vec<Value> = arguments.collect();
+
+    if arguments.is_empty() {
+        return Err(argument_error());
+    }
+
+    if arguments.iter().any(is_lazy) {
+        return Ok(lazy_map(function, arguments));
+    }
+
+    let collections = arguments
+        .into_iter()
+        .map(|collection| sequence_values(interpreter, collection))
+        .collect::<Result<Vec<Vec<Value>>, Error>>()?;
+
+    let length = collections.iter().map(|values| values.len()).min().unwrap();
+    let mut iterators: Vec<_> = collections.into_iter().map(|c| c.into_iter()).collect();
+    let mut results = Vec::with_capacity(length);
+
+    for _ in 0..length {
+        let values = iterators
+            .iter_mut()
+            .map(|iterator| iterator.next().unwrap())
+            .collect();
+
+        results.push(interpreter.call_value(&function, values)?);
+    }
+
+    return Ok(Value::List(results.into()));
+}
+
+fn filter(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [predicate, collection]: [Value; 2] = arguments.try_into().map_err(|_| argument_error())?;
+
+    if is_lazy(&collection) {
+        return Ok(lazy_filter(predicate, collection));
+    }
+
+    let mut results = Vec::new();
+
+    for value in sequence_values(interpreter, collection)? {
+        if interpreter
+            .call_value(&predicate, vec![value.clone()])?
+            .is_truthy()
+        {
+            results.push(value);
+        }
+    }
+
+    return Ok(Value::List(results.into()));
+}
+
+/// `(reduce f coll)` or `(reduce f initial coll)`. Without an initial value
+/// the first element is used, and an empty collection reduces to `(f)`.
+fn reduce(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let (function, initial, collection) = match <[Value; 3]>::try_from(arguments) {
+        Ok([function, initial, collection]) => (function, Some(initial), collection),
+
+        Err(arguments) => match <[Value; 2]>::try_from(arguments) {
+            Ok([function, collection]) => (function, None, collection),
+            Err(_) => return Err(argument_error()),
+        },
+    };
+
+    let mut values = sequence_values(interpreter, collection)?.into_iter();
+
+    let mut accumulator = match initial {
+        Some(initial) => initial,
+        None => match values.next() {
+            Some(value) => value,
+            None => return interpreter.call_value(&function, Vec::new()),
+        },
+    };
+
+    for value in values {
+        accumulator = interpreter.call_value(&function, vec![accumulator, value])?;
+    }
+
+    return Ok(accumulator);
+}
+
+/// `(range end)`, `(range start end)` or `(range start end step)`. Without
+/// arguments `(range)` is the infinite sequence of natural numbers.
+fn range(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let numbers = arguments
+        .iter()
+        .map(number_argument)
+        .collect::<Result<Vec<i64>, Error>>()?;
+
+    let (start, end, step) = match numbers[..] {
+        [] => return Ok(lazy_range(0, 1)),
+        [end] => (0, end, 1),
+        [start, end] => (start, end, 1),
+        [_, _, 0] => {
+            return Err(Error::new(
+                "range step must not be zero",
+                ErrorType::ArgumentError,
+            ))
+        }
+        [start, end, step] => (start, end, step),
+        _ => return Err(argument_error()),
+    };
+
+    let mut values = Vec::new();
+    let mut current = Some(start);
+
+    // Stepping past the bounds of a number also ends the range.
+    while let Some(number) = current {
+        if (step > 0 && number >= end) || (step < 0 && number <= end) {
+            break;
+        }
+
+        values.push(Value::Number(number));
+        current = number.checked_add(step);
+    }
+
+    return Ok(Value::List(values.into()));
+}
+
+fn take(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [count, collection]: [Value; 2] = arguments.try_into().map_err(|_| argument_error())?;
+    let count = number_argument(&count)?;
+
+    if is_lazy(&collection) {
+        return Ok(lazy_take(count, collection));
+    }
+
+    let mut values = sequence_values(interpreter, collection)?;
+    values.truncate(count.max(0) as usize);
+
+    return Ok(Value::List(values.into()));
+}
+
+fn drop(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [count, collection]: [Value; 2] = arguments.try_into().map_err(|_| argument_error())?;
+    let count = number_argument(&count)?;
+
+    if is_lazy(&collection) {
+        return Ok(lazy_drop(count, collection));
+    }
+
+    let values = sequence_values(interpreter, collection)?;
+
+    return Ok(Value::List(
+        values.into_iter().skip(count.max(0) as usize).collect(),
+    ));
+}
+
+fn reverse(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [collection]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+
+    let mut values = sequence_values(interpreter, collection)?;
+    values.reverse();
+
+    return Ok(Value::List(values.into()));
+}
+
+/// `(nth coll index)` fails when the index is out of bounds, while
+/// `(nth coll index default)` returns `default` instead.
+fn nth(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let mut arguments = arguments.into_iter();
+
+    let (collection, index, default) = match (
+        arguments.next(),
+        arguments.next(),
+        arguments.next(),
+        arguments.next(),
+    ) {
+        (Some(collection), Some(index), default, None) => (collection, index, default),
+        _ => return Err(argument_error()),
+    };
+
+    let index = number_argument(&index)?;
+
+    if is_lazy(&collection) {
+        // No element has a negative index, and walking a sequence to find
+        // one would never end on an infinite sequence. Its length isn't known
+        // without realizing it, so none of it counts.
+        if index < 0 {
+            return default.ok_or(Error::new(
+                "Index out of bounds",
+                ErrorType::IndexOutOfBounds { index, length: 0 },
+            ));
+        }
+
+        let mut sequence = collection;
+        let mut length = 0;
+
+        while let Some((first, rest)) = interpreter.sequence_step(sequence)? {
+            if usize::try_from(index) == Ok(length) {
+                return Ok(first);
+            }
+
+            length += 1;
+            sequence = rest;
+        }
+
+        return default.ok_or(Error::new(
+            "Index out of bounds",
+            ErrorType::IndexOutOfBounds { index, length },
+        ));
+    }
+
+    let values = sequence_values(interpreter, collection)?;
+    let length = values.len();
+
+    match usize::try_from(index)
+        .ok()
+        .and_then(|index| values.into_iter().nth(index))
+    {
+        Some(value) => Ok(value),
+        None => default.ok_or(Error::new(
+            "Index out of bounds",
+            ErrorType::IndexOutOfBounds { index, length },
+        )),
+    }
+}
+
+fn length(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    match &arguments[..] {
+        [Value::List(values)] => Ok(Value::Number(values.len() as i64)),
+        [Value::Map(map)] => Ok(Value::Number(map.len() as i64)),
+        [Value::String(string)] => Ok(Value::Number(string.chars().count() as i64)),
+        [Value::Nil] => Ok(Value::Number(0)),
+        [Value::LazySequence(_)] => {
+            let sequence = arguments.into_iter().next().unwrap();
+            Ok(Value::Number(
+                sequence_values(interpreter, sequence)?.len() as i64
+            ))
+        }
+        [value] => Err(type_error(Type::List, value)),
+        _ => Err(argument_error()),
+    }
+}
+
+fn concat(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    if arguments.iter().any(is_lazy) {
+        let mut sequences = steppable(interpreter, arguments)?;
+        sequences.reverse();
+
+        return Ok(lazy_concat(sequences));
+    }
+
+    let mut values = Vec::new();
+
+    for collection in arguments {
+        values.extend(sequence_values(interpreter, collection)?);
+    }
+
+    return Ok(Value::List(values.into()));
+}
+
+/// `(sort coll)` sorts numbers or strings in ascending order, while
+/// `(sort less-than coll)` orders elements with a user supplied predicate.
+fn sort(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let (comparator, collection) = match <[Value; 2]>::try_from(arguments) {
+        Ok([comparator, collection]) => (Some(comparator), collection),
+
+        Err(arguments) => match <[Value; 1]>::try_from(arguments) {
+            Ok([collection]) => (None, collection),
+            Err(_) => return Err(argument_error()),
+        },
+    };
+
+    let values = sequence_values(interpreter, collection)?;
+
+    let sorted = match comparator {
+        Some(comparator) => merge_sort(values, |a, b| {
+            Ok(interpreter
+                .call_value(&comparator, vec![a.clone(), b.clone()])?
+                .is_truthy())
+        })?,
+
+        None => merge_sort(values, |a, b| match (a, b) {
+            (Value::Number(a), Value::Number(b)) => Ok(a < b),
+            (Value::String(a), Value::String(b)) => Ok(a < b),
+            (Value::Number(_), b) => Err(type_error(Type::Number, b)),
+            (Value::String(_), b) => Err(type_error(Type::String, b)),
+            (a, _) => Err(type_error(Type::Number, a)),
+        })?,
+    };
+
+    return Ok(Value::List(sorted.into()));
+}
+
+/// A stable, bottom-up merge sort. Unlike `slice::sort_by` it tolerates
+/// comparators that fail or do not describe a total order.
+fn merge_sort<F>(values: Vec<Value>, mut less_than: F) -> Result<Vec<Value>, Error>
+where
+    F: FnMut(&Value, &Value) -> Result<bool, Error>,
+{
+    // Sort a permutation of indices so that values are only moved once.
+    let length = values.len();
+    let mut indices: Vec<usize> = (0..length).collect();
+    let mut buffer: Vec<usize> = Vec::with_capacity(length);
+    let mut width = 1;
+
+    while width < length {
+        buffer.clear();
+
+        for start in (0..length).step_by(2 * width) {
+            let middle = (start + width).min(length);
+            let end = (start + 2 * width).min(length);
+            let (mut left, mut right) = (start, middle);
+
+            while left < middle && right < end {
+                if less_than(&values[indices[right]], &values[indices[left]])? {
+                    buffer.push(indices[right]);
+                    right += 1;
+                } else {
+                    buffer.push(indices[left]);
+                    left += 1;
+                }
+            }
+
+            buffer.extend_from_slice(&indices[left..middle]);
+            buffer.extend_from_slice(&indices[right..end]);
+        }
+
+        std::mem::swap(&mut indices, &mut buffer);
+        width *= 2;
+    }
+
+    let mut values: Vec<Option<Value>> = values.into_iter().map(Some).collect();
+
+    return Ok(indices
+        .into_iter()
+        .map(|index| values[index].take().unwrap())
+        .collect());
+}
+
+/// `(zip coll ...)` pairs up the elements of every collection, stopping at
+/// the end of the shortest one.
+fn zip(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    if arguments.iter().any(is_lazy) {
+        return Ok(lazy_zip(steppable(interpreter, arguments)?));
+    }
+
+    let collections = arguments
+        .into_iter()
+        .map(|collection| sequence_values(interpreter, collection))
+        .collect::<Result<Vec<Vec<Value>>, Error>>()?;
+
+    let length = collections
+        .iter()
+        .map(|values| values.len())
+        .min()
+        .unwrap_or(0);
+    let mut iterators: Vec<_> = collections.into_iter().map(|c| c.into_iter()).collect();
+
+    let values = (0..length)
+        .map(|_| {
+            Value::List(
+                iterators
+                    .iter_mut()
+                    .map(|iterator| iterator.next().unwrap())
+                    .collect(),
+            )
+        })
+        .collect();
+
+    return Ok(Value::List(values));
+}
+
+/// Flattens arbitrarily nested lists into a single list.
+fn flatten(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, Error> {
+    let [collection]: [Value; 1] = arguments.try_into().map_err(|_| argument_error())?;
+
+    let mut values = Vec::new();
+    let mut stack = vec![sequence_values(interpreter, collection)?.into_iter()];
+
+    while let Some(iterator) = stack.last_mut() {
+        match iterator.next() {
+            Some(Value::List(nested)) => stack.push(nested.into_iter()),
+            Some(value) => values.push(value),
+            None => {
+                stack.pop();
+            }
+        }
+    }
+
+    return Ok(Value::List(values.into()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::{display, evaluate_source};
+
+    #[test]
+    fn test_map_filter_reduce() {
+        assert_eq!(display("(map (fn [a] (add a 1)) (list 1 2 3))"), "(2 3 4)");
+        assert_eq!(display("(map add (list 1 2 3) (list 10 20))"), "(11 22)");
+        assert_eq!(
+            display("(filter (fn [a] (is-greater a 1)) (list 1 2 0 3))"),
+            "(2 3)"
+        );
+        assert_eq!(display("(reduce add (list 1 2 3))"), "6");
+        assert_eq!(display("(reduce add 10 (list 1 2 3))"), "16");
+        assert_eq!(display("(reduce add 5 (list))"), "5");
+    }
+
+    #[test]
+    fn test_slicing_sequences() {
+        assert_eq!(display("(range 3)"), "(0 1 2)");
+        assert_eq!(display("(range 5 0 -2)"), "(5 3 1)");
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(range 0 10 0)"),
+            Err(Error::new(
+                "range step must not be zero",
+                ErrorType::ArgumentError
+            ))
+        );
+        assert_eq!(
+            display("(range 9223372036854775800 9223372036854775807 5)"),
+            "(9223372036854775800 9223372036854775805)"
+        );
+        assert_eq!(
+            display("(range -9223372036854775800 -9223372036854775807 -5)"),
+            "(-9223372036854775800 -9223372036854775805)"
+        );
+        assert_eq!(display("(take 2 (range 10))"), "(0 1)");
+        assert_eq!(display("(drop 8 (range 10))"), "(8 9)");
+        assert_eq!(display("(reverse (list 1 2 3))"), "(3 2 1)");
+        assert_eq!(display("(nth (list 1 2 3) 1)"), "2");
+        assert_eq!(display("(nth (list 1 2 3) 5 nil)"), "nil");
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(nth (list 1 2 3) 5)"),
+            Err(Error::new(
+                "Index out of bounds",
+                ErrorType::IndexOutOfBounds {
+                    index: 5,
+                    length: 3
+                },
+            ))
+        );
+    }
+
+    #[test]
+    fn test_combining_sequences() {
+        assert_eq!(display("(length (list 1 2 3))"), "3");
+        assert_eq!(display("(length \"abc\")"), "3");
+        assert_eq!(
+            display("(concat (list 1) (list 2 3) \"ab\")"),
+            "(1 2 3 a b)"
+        );
+        assert_eq!(display("(zip (list 1 2 3) (list 4 5))"), "((1 4) (2 5))");
+        assert_eq!(
+            display("(flatten (list 1 (list 2 (list 3 (list))) 4))"),
+            "(1 2 3 4)"
+        );
+        assert_eq!(display("(map car {\"a\" 1})"), "(a)");
+    }
+
+    #[test]
+    fn test_sorting() {
+        assert_eq!(display("(sort (list 3 1 2))"), "(1 2 3)");
+        assert_eq!(display("(sort (list \"b\" \"a\"))"), "(a b)");
+        assert_eq!(display("(sort is-greater (list 1 3 2))"), "(3 2 1)");
+        assert_eq!(
+            evaluate_source(&mut Interpreter::new(), "(sort (list 1 \"a\"))"),
+            Err(type_error(Type::String, &Value::Number(1)))
+        );
+    }
+
+    #[test]
+    fn test_large_sequences() {
+        assert_eq!(display("(reduce add (range 1000000))"), "499999500000");
+        assert_eq!(
+            display(
+                "(length (filter (fn [a] (is-greater a 500000))
+                                 (map (fn [a] (add a 1)) (range 1000000))))"
+            ),
+            "500000"
+        );
+        assert_eq!(
+            display("(nth (sort (reverse (range 1000000))) 999999)"),
+            "999999"
+        );
+        assert_eq!(
+            display("(length (flatten (zip (range 1000000) (range 1000000))))"),
+            "2000000"
+        );
+    }
+}
@@ -1,11 +1,16 @@
+mod checker;
 mod error;
 mod interpreter;
 mod parser;
 mod tokenizer;
 mod value;
 
-pub use error::{Error, ErrorType};
+pub use checker::{check, check_source, Mismatch};
+pub use error::{CallFrame, Error, ErrorType, Warning};
 pub use interpreter::Interpreter;
-pub use parser::{parse_node, ASTNode};
-pub use tokenizer::{tokenize, Token};
+pub use parser::{
+    parse_node, ASTNode, Conditions, FunctionArity, ParameterList, Pattern, TokenStream,
+    TypeAnnotation,
+};
+pub use tokenizer::{tokenize, tokenize_with_spans, Span, Token};
 pub use value::{Type, Value};
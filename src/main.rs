@@ -1,13 +1,56 @@
 use risp::{Error, Interpreter, Value};
-use std::io::Write;
+use std::{io::Write, path::PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     #[clap(value_parser)]
     filename: Option<String>,
+
+    /// Evaluate undefined variables to nil instead of failing
+    #[clap(long)]
+    undefined_as_nil: bool,
+
+    /// Search a directory for required modules, after the one of the file
+    /// requiring them. Directories in RISP_PATH are searched last
+    #[clap(long, value_parser)]
+    module_path: Vec<PathBuf>,
+
+    /// Skip the :pre and :post conditions of functions
+    #[clap(long)]
+    no_contracts: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the types of a program without running it
+    Check {
+        #[clap(value_parser)]
+        filename: String,
+    },
+}
+
+fn create_interpreter(cli: &Cli) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_undefined_as_nil(cli.undefined_as_nil);
+    interpreter.set_check_contracts(!cli.no_contracts);
+
+    for path in cli.module_path.iter() {
+        interpreter.add_module_path(path);
+    }
+
+    if let Some(paths) = std::env::var_os("RISP_PATH") {
+        for path in std::env::split_paths(&paths) {
+            interpreter.add_module_path(path);
+        }
+    }
+
+    return interpreter;
 }
 
 fn prompt(name: &str) -> String {
@@ -24,22 +67,30 @@ fn prompt(name: &str) -> String {
 pub fn main() {
     let cli = Cli::parse();
 
+    if let Some(Command::Check { filename }) = &cli.command {
+        if !check_file(filename) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     match &cli.filename {
         Some(filename) => {
-            let mut interpreter = Interpreter::new();
+            let mut interpreter = create_interpreter(&cli);
 
-            match interpreter.evaluate_file(&filename) {
-                Ok(_) => (),
-                Err(error) => {
-                    println!("{:?}", error);
-                    std::process::exit(1);
-                }
+            let result = interpreter.evaluate_file(filename);
+            print_warnings(&mut interpreter, filename);
+
+            if let Err(error) = result {
+                print_error(&error, filename);
+                std::process::exit(1);
             }
         }
 
         None => {
             println!("Welcome to RISP 🎉\n");
-            let mut interpreter = Interpreter::new();
+            let mut interpreter = create_interpreter(&cli);
 
             loop {
                 let expression = prompt("> ");
@@ -48,18 +99,85 @@ pub fn main() {
                     continue;
                 }
 
-                match parse_and_evaluate(&mut interpreter, &expression) {
+                let result = parse_and_evaluate(&mut interpreter, &expression);
+                print_warnings(&mut interpreter, "<repl>");
+
+                match result {
                     Ok(value) => println!("{}", value.to_display_string()),
-                    Err(error) => println!("{:?}", error),
+                    Err(error) => print_error(&error, "<repl>"),
                 }
             }
         }
     }
 }
 
+/// Prints the type mismatches in a file, returning whether there were none.
+fn check_file(filename: &str) -> bool {
+    let source = match std::fs::read_to_string(filename) {
+        Ok(source) => source,
+        Err(error) => {
+            println!("Error: could not read {}: {}", filename, error);
+            return false;
+        }
+    };
+
+    let mismatches = match risp::check_source(&source) {
+        Ok(mismatches) => mismatches,
+        Err(error) => {
+            print_error(&error, filename);
+            return false;
+        }
+    };
+
+    for mismatch in mismatches.iter() {
+        println!(
+            "Type error: {} at {}:{}:{}",
+            mismatch.message, filename, mismatch.span.line, mismatch.span.column
+        );
+    }
+
+    return mismatches.is_empty();
+}
+
+fn print_warnings(interpreter: &mut Interpreter, source_name: &str) {
+    for warning in interpreter.take_warnings() {
+        let file = match &warning.file {
+            Some(file) => file.display().to_string(),
+            None => source_name.to_string(),
+        };
+
+        eprintln!(
+            "Warning: {} at {}:{}:{}",
+            warning.message, file, warning.span.line, warning.span.column
+        );
+    }
+}
+
+/// Prints an error followed by the calls that led to it, innermost first.
+fn print_error(error: &Error, source_name: &str) {
+    println!("Error: {}", error);
+
+    for frame in error.backtrace() {
+        let file = match &frame.file {
+            Some(file) => file.display().to_string(),
+            None => source_name.to_string(),
+        };
+
+        match frame.span {
+            Some(span) => println!(
+                "    at {} ({}) {}:{}:{}",
+                frame.function, frame.arguments, file, span.line, span.column
+            ),
+            None => println!("    at {} ({})", frame.function, frame.arguments),
+        }
+    }
+}
+
 fn parse_and_evaluate(interpreter: &mut Interpreter, input: &str) -> Result<Value, Error> {
-    let tokens = risp::tokenize(input);
-    let expression = risp::parse_node(&mut tokens.into_iter().peekable())?;
+    let tokens = risp::tokenize_with_spans(input);
+    let expression = risp::parse_node(&mut tokens.into())?;
+
+    let value = interpreter.evaluate(&expression)?;
 
-    return interpreter.evaluate(&expression);
+    return interpreter.realize(value);
 }
@@ -1,16 +1,22 @@
-use std::{iter::Peekable, vec::IntoIter};
+use std::{iter::Peekable, rc::Rc, vec::IntoIter};
 
-use crate::{Error, ErrorType, Token};
+use crate::{tokenizer::Span, Error, ErrorType, Token};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ASTNode {
     NumberLiteral(i64),
     BooleanLiteral(bool),
+    NilLiteral,
     Identifier(String),
     StringLiteral(String),
+    /// A keyword such as `:name`, by its name without the colon.
+    KeywordLiteral(String),
 
-    CallExpression(String, Vec<ASTNode>),
+    /// A call of the callee with the arguments, made at the span of its
+    /// opening parenthesis.
+    CallExpression(Box<ASTNode>, Vec<ASTNode>, Span),
     ListExpression(Vec<ASTNode>),
+    MapExpression(Vec<(ASTNode, ASTNode)>),
 
     IfExpression {
         expression: Box<ASTNode>,
@@ -18,34 +24,652 @@ pub enum ASTNode {
         when_false: Box<ASTNode>,
     },
 
+    /// `(defn name "docstring" [parameters] body)`, where the docstring is
+    /// optional.
     FunctionDeclaration {
         identifier: String,
-        parameter_list: Vec<String>,
-        body: Box<ASTNode>,
+        docstring: Option<Rc<str>>,
+        arities: Vec<FunctionArity>,
+    },
+
+    FunctionExpression {
+        arities: Vec<FunctionArity>,
+    },
+
+    /// `(defmethod area "circle" [shape] body)` adds the method used when
+    /// the dispatch function of the multimethod `area` returns `"circle"`.
+    MethodDeclaration {
+        identifier: String,
+        dispatch_value: Box<ASTNode>,
+        arities: Vec<FunctionArity>,
+    },
+
+    /// `(extend-type String Shape (area [s] body)...)` implements the
+    /// methods of the protocol `Shape` for strings.
+    TypeExtension {
+        type_name: String,
+        protocol: String,
+        methods: Vec<(String, Vec<FunctionArity>)>,
+    },
+
+    LetExpression {
+        bindings: Vec<(Pattern, ASTNode)>,
+        body: Vec<ASTNode>,
+    },
+
+    /// `(try? [a x b y] body)` binds like `let` but unwraps `ok` results and
+    /// short-circuits with the first `err`.
+    TryLetExpression {
+        bindings: Vec<(Pattern, ASTNode)>,
+        body: Vec<ASTNode>,
+    },
+
+    /// `^{:private true} expression` attaches the metadata map to the
+    /// definition or value of the expression.
+    MetadataExpression {
+        metadata: Box<ASTNode>,
+        expression: Box<ASTNode>,
     },
 }
 
-pub fn parse(tokens: &mut Peekable<std::vec::IntoIter<Token>>) -> Result<Vec<ASTNode>, Error> {
-    let mut nodes = Vec::new();
+/// One body of a (possibly multi-arity) function declaration.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionArity {
+    pub parameter_list: ParameterList,
+    /// The type written after the parameter list, e.g. `[a] : Number`.
+    pub return_type: Option<TypeAnnotation>,
+    pub conditions: Conditions,
+    pub body: ASTNode,
+}
 
-    loop {
-        if tokens.len() > 0 {
-            nodes.push(parse_node(tokens)?);
+/// The `{:pre [...] :post [...]}` conditions written before the body of a
+/// function, checked on every call. Postconditions see the return value as
+/// `%`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Conditions {
+    pub pre: Vec<ASTNode>,
+    pub post: Vec<ASTNode>,
+}
+
+impl Conditions {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+}
+
+/// A type written after a colon, e.g. `Number` in `[a : Number]`. The
+/// checker uses annotations, the interpreter ignores them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeAnnotation {
+    pub name: String,
+    pub span: Span,
+}
+
+/// The parameters of a function, in the order they are bound: required
+/// positional parameters, `&optional` parameters, `&key` parameters and a
+/// `&` rest parameter.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ParameterList {
+    pub required: Vec<Pattern>,
+    pub optional: Vec<(String, Option<ASTNode>)>,
+    pub keywords: Vec<(String, Option<ASTNode>)>,
+    pub rest: Option<Pattern>,
+    /// The annotated types of named parameters, e.g. `a : Number`.
+    pub types: Vec<(String, TypeAnnotation)>,
+}
+
+impl ParameterList {
+    /// The smallest number of positional arguments accepted.
+    pub fn minimum_arity(&self) -> usize {
+        self.required.len()
+    }
+
+    /// The largest number of positional arguments accepted, if bounded.
+    pub fn maximum_arity(&self) -> Option<usize> {
+        if self.rest.is_some() {
+            None
         } else {
-            break;
+            Some(self.required.len() + self.optional.len())
+        }
+    }
+
+    pub fn accepts(&self, argument_count: usize) -> bool {
+        argument_count >= self.minimum_arity()
+            && self
+                .maximum_arity()
+                .is_none_or(|maximum| argument_count <= maximum)
+    }
+
+    /// Every name the parameters bind, in order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+
+        for pattern in self.required.iter() {
+            pattern.collect_names(&mut names);
+        }
+
+        names.extend(self.optional.iter().map(|(name, _)| name.as_str()));
+        names.extend(self.keywords.iter().map(|(name, _)| name.as_str()));
+
+        if let Some(pattern) = &self.rest {
+            pattern.collect_names(&mut names);
+        }
+
+        return names;
+    }
+}
+
+/// The left-hand side of a binding, used by parameter lists and `let`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    /// Binds the whole value to a name, e.g. `a`.
+    Identifier(String),
+
+    /// Binds the elements of a list, e.g. `[a [b c] & rest]`.
+    Sequence {
+        elements: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+    },
+
+    /// Binds entries of a map to names matching their keys, e.g. `{:keys [x y]}`.
+    Map { keys: Vec<String> },
+}
+
+/// The tokens being parsed, along with the positions they start at.
+pub struct TokenStream {
+    tokens: Peekable<IntoIter<(Token, Span)>>,
+}
+
+impl TokenStream {
+    pub fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek().map(|(token, _)| token)
+    }
+
+    /// The position of the next token, or the default span at the end.
+    pub fn span(&mut self) -> Span {
+        self.tokens
+            .peek()
+            .map(|(_, span)| *span)
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        self.tokens.peek().is_none()
+    }
+}
+
+impl Iterator for TokenStream {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.tokens.next().map(|(token, _)| token)
+    }
+}
+
+impl From<Vec<(Token, Span)>> for TokenStream {
+    fn from(tokens: Vec<(Token, Span)>) -> Self {
+        Self {
+            tokens: tokens.into_iter().peekable(),
+        }
+    }
+}
+
+/// Tokens without positions, e.g. built by hand, all get the default span.
+impl From<Vec<Token>> for TokenStream {
+    fn from(tokens: Vec<Token>) -> Self {
+        Self::from(
+            tokens
+                .into_iter()
+                .map(|token| (token, Span::default()))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+impl Pattern {
+    pub(crate) fn collect_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Pattern::Identifier(name) => names.push(name),
+
+            Pattern::Sequence { elements, rest } => {
+                for element in elements.iter() {
+                    element.collect_names(names);
+                }
+
+                if let Some(rest) = rest {
+                    rest.collect_names(names);
+                }
+            }
+
+            Pattern::Map { keys } => names.extend(keys.iter().map(|key| key.as_str())),
+        }
+    }
+}
+
+/// Joins the source of several nodes with spaces.
+fn join_nodes<T: std::fmt::Display>(nodes: &[T]) -> String {
+    return nodes
+        .iter()
+        .map(|node| node.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+}
+
+/// Writes a node back as risp source, e.g. `(add a 1)`.
+impl std::fmt::Display for ASTNode {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ASTNode::NumberLiteral(number) => write!(formatter, "{}", number),
+            ASTNode::BooleanLiteral(value) => write!(formatter, "{}", value),
+            ASTNode::NilLiteral => write!(formatter, "nil"),
+            ASTNode::Identifier(name) => write!(formatter, "{}", name),
+            ASTNode::StringLiteral(string) => write!(formatter, "\"{}\"", string),
+            ASTNode::KeywordLiteral(name) => write!(formatter, ":{}", name),
+
+            ASTNode::CallExpression(callee, arguments, _) if arguments.is_empty() => {
+                write!(formatter, "({})", callee)
+            }
+            ASTNode::CallExpression(callee, arguments, _) => {
+                write!(formatter, "({} {})", callee, join_nodes(arguments))
+            }
+            ASTNode::ListExpression(nodes) => write!(formatter, "[{}]", join_nodes(nodes)),
+            ASTNode::MapExpression(entries) => write!(
+                formatter,
+                "{{{}}}",
+                entries
+                    .iter()
+                    .map(|(key, value)| format!("{} {}", key, value))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+
+            ASTNode::IfExpression {
+                expression,
+                when_true,
+                when_false,
+            } => write!(
+                formatter,
+                "(if {} {} {})",
+                expression, when_true, when_false
+            ),
+
+            ASTNode::FunctionDeclaration {
+                identifier,
+                docstring: Some(docstring),
+                arities,
+            } => write!(
+                formatter,
+                "(defn {} \"{}\" {})",
+                identifier,
+                docstring,
+                Arities(arities)
+            ),
+            ASTNode::FunctionDeclaration {
+                identifier,
+                docstring: None,
+                arities,
+            } => write!(formatter, "(defn {} {})", identifier, Arities(arities)),
+            ASTNode::FunctionExpression { arities } => {
+                write!(formatter, "(fn {})", Arities(arities))
+            }
+            ASTNode::MethodDeclaration {
+                identifier,
+                dispatch_value,
+                arities,
+            } => write!(
+                formatter,
+                "(defmethod {} {} {})",
+                identifier,
+                dispatch_value,
+                Arities(arities)
+            ),
+            ASTNode::TypeExtension {
+                type_name,
+                protocol,
+                methods,
+            } => write!(
+                formatter,
+                "(extend-type {} {} {})",
+                type_name,
+                protocol,
+                methods
+                    .iter()
+                    .map(|(name, arities)| format!("({} {})", name, Arities(arities)))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+
+            ASTNode::LetExpression { bindings, body } => {
+                write!(formatter, "(let {})", LetForm(bindings, body))
+            }
+            ASTNode::TryLetExpression { bindings, body } => {
+                write!(formatter, "(try? {})", LetForm(bindings, body))
+            }
+            ASTNode::MetadataExpression {
+                metadata,
+                expression,
+            } => write!(formatter, "^{} {}", metadata, expression),
+        }
+    }
+}
+
+/// The width `to_pretty_string` keeps lines within where it can.
+const LINE_WIDTH: usize = 80;
+
+impl ASTNode {
+    /// Writes the node back as risp source like `to_string` does, breaking
+    /// forms that don't fit on a line over several lines.
+    pub fn to_pretty_string(&self) -> String {
+        return pretty_print(self, 0);
+    }
+}
+
+/// Prints `node` as if it started `indent` columns into its line.
+fn pretty_print(node: &ASTNode, indent: usize) -> String {
+    let flat = node.to_string();
+
+    if indent + flat.chars().count() <= LINE_WIDTH {
+        return flat;
+    }
+
+    // The head of the form and the parts that go on their own lines.
+    let (head, parts): (String, Vec<String>) = match node {
+        ASTNode::CallExpression(callee, arguments, _) => (
+            callee.to_string(),
+            arguments
+                .iter()
+                .map(|argument| pretty_print(argument, indent + 2))
+                .collect(),
+        ),
+
+        ASTNode::IfExpression {
+            expression,
+            when_true,
+            when_false,
+        } => (
+            format!("if {}", pretty_print(expression, indent + 4)),
+            vec![
+                pretty_print(when_true, indent + 2),
+                pretty_print(when_false, indent + 2),
+            ],
+        ),
+
+        ASTNode::FunctionDeclaration {
+            identifier,
+            docstring,
+            arities,
+        } => {
+            let (parameters, arity_parts) = pretty_print_arities(arities, indent);
+            let mut head = format!("defn {}", identifier);
+            let mut parts = Vec::new();
+
+            // The parameters of a single arity follow the docstring if there
+            // is one, or else the name.
+            match docstring {
+                Some(docstring) => {
+                    parts.push(format!("\"{}\"", docstring));
+                    parts.extend(parameters);
+                }
+                None => head.extend(parameters.map(|parameters| format!(" {}", parameters))),
+            }
+
+            parts.extend(arity_parts);
+            (head, parts)
+        }
+
+        ASTNode::FunctionExpression { arities } => {
+            let (parameters, parts) = pretty_print_arities(arities, indent);
+            let mut head = "fn".to_string();
+            head.extend(parameters.map(|parameters| format!(" {}", parameters)));
+            (head, parts)
+        }
+
+        // The metadata goes on a line of its own above the expression.
+        ASTNode::MetadataExpression {
+            metadata,
+            expression,
+        } => {
+            return format!(
+                "^{}\n{}{}",
+                metadata,
+                " ".repeat(indent),
+                pretty_print(expression, indent)
+            )
+        }
+
+        ASTNode::LetExpression { bindings, body }
+        | ASTNode::TryLetExpression { bindings, body } => {
+            let keyword = match node {
+                ASTNode::LetExpression { .. } => "let",
+                _ => "try?",
+            };
+            let bindings = bindings
+                .iter()
+                .map(|(pattern, value)| {
+                    let pattern = pattern.to_string();
+                    let value_indent = indent + keyword.len() + 3 + pattern.chars().count();
+                    format!("{} {}", pattern, pretty_print(value, value_indent))
+                })
+                .collect::<Vec<String>>()
+                .join(&format!("\n{}", " ".repeat(indent + keyword.len() + 3)));
+
+            (
+                format!("{} [{}]", keyword, bindings),
+                body.iter()
+                    .map(|node| pretty_print(node, indent + 2))
+                    .collect(),
+            )
+        }
+
+        _ => return flat,
+    };
+
+    let separator = format!("\n{}", " ".repeat(indent + 2));
+
+    return format!("({}{}{})", head, separator, parts.join(&separator));
+}
+
+/// The parameter list that starts a single arity and the parts that follow
+/// it, or no parameter list and a part for every arity of a multi-arity
+/// function.
+fn pretty_print_arities(arities: &[FunctionArity], indent: usize) -> (Option<String>, Vec<String>) {
+    match arities {
+        [arity] => {
+            let mut parameters = arity.parameter_list.to_string();
+
+            if let Some(return_type) = &arity.return_type {
+                parameters.push_str(&format!(" : {}", return_type.name));
+            }
+
+            let mut parts = Vec::new();
+
+            if !arity.conditions.is_empty() {
+                parts.push(arity.conditions.to_string());
+            }
+
+            parts.push(pretty_print(&arity.body, indent + 2));
+
+            (Some(parameters), parts)
+        }
+        arities => (
+            None,
+            arities
+                .iter()
+                .map(|arity| {
+                    let flat = format!("({})", arity);
+
+                    if indent + 2 + flat.chars().count() <= LINE_WIDTH {
+                        return flat;
+                    }
+
+                    let (parameters, parts) =
+                        pretty_print_arities(std::slice::from_ref(arity), indent + 1);
+                    let separator = format!("\n{}", " ".repeat(indent + 3));
+                    format!(
+                        "({}{}{})",
+                        parameters.unwrap_or_default(),
+                        separator,
+                        parts.join(&separator)
+                    )
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// The arities of a function as written after its name.
+struct Arities<'a>(&'a [FunctionArity]);
+
+impl std::fmt::Display for Arities<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            [arity] => write!(formatter, "{}", arity),
+            arities => write!(
+                formatter,
+                "{}",
+                arities
+                    .iter()
+                    .map(|arity| format!("({})", arity))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+        }
+    }
+}
+
+/// The bindings and body of a `let`.
+struct LetForm<'a>(&'a [(Pattern, ASTNode)], &'a [ASTNode]);
+
+impl std::fmt::Display for LetForm<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bindings = self
+            .0
+            .iter()
+            .map(|(pattern, value)| format!("{} {}", pattern, value))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        write!(formatter, "[{}] {}", bindings, join_nodes(self.1))
+    }
+}
+
+impl std::fmt::Display for FunctionArity {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}", self.parameter_list)?;
+
+        if let Some(return_type) = &self.return_type {
+            write!(formatter, " : {}", return_type.name)?;
+        }
+
+        if !self.conditions.is_empty() {
+            write!(formatter, " {}", self.conditions)?;
+        }
+
+        write!(formatter, " {}", self.body)
+    }
+}
+
+impl std::fmt::Display for Conditions {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut entries = Vec::new();
+
+        if !self.pre.is_empty() {
+            entries.push(format!(":pre [{}]", join_nodes(&self.pre)));
+        }
+
+        if !self.post.is_empty() {
+            entries.push(format!(":post [{}]", join_nodes(&self.post)));
+        }
+
+        write!(formatter, "{{{}}}", entries.join(" "))
+    }
+}
+
+impl std::fmt::Display for ParameterList {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let annotated =
+            |name: &str| match self.types.iter().find(|(parameter, _)| parameter == name) {
+                Some((_, annotation)) => format!("{} : {}", name, annotation.name),
+                None => name.to_string(),
+            };
+        let defaulted = |(name, default): &(String, Option<ASTNode>)| match default {
+            Some(default) => format!("({} {})", annotated(name), default),
+            None => annotated(name),
+        };
+
+        let mut parameters = Vec::new();
+
+        for pattern in self.required.iter() {
+            parameters.push(match pattern {
+                Pattern::Identifier(name) => annotated(name),
+                pattern => pattern.to_string(),
+            });
+        }
+
+        if !self.optional.is_empty() {
+            parameters.push("&optional".to_string());
+            parameters.extend(self.optional.iter().map(defaulted));
+        }
+
+        if !self.keywords.is_empty() {
+            parameters.push("&key".to_string());
+            parameters.extend(self.keywords.iter().map(defaulted));
+        }
+
+        if let Some(rest) = &self.rest {
+            parameters.push("&".to_string());
+            parameters.push(match rest {
+                Pattern::Identifier(name) => annotated(name),
+                pattern => pattern.to_string(),
+            });
+        }
+
+        write!(formatter, "[{}]", parameters.join(" "))
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Identifier(name) => write!(formatter, "{}", name),
+            Pattern::Sequence { elements, rest } => {
+                let mut parts: Vec<String> =
+                    elements.iter().map(|element| element.to_string()).collect();
+
+                if let Some(rest) = rest {
+                    parts.push(format!("& {}", rest));
+                }
+
+                write!(formatter, "[{}]", parts.join(" "))
+            }
+            Pattern::Map { keys } => write!(formatter, "{{:keys [{}]}}", keys.join(" ")),
         }
     }
+}
+
+pub fn parse(tokens: &mut TokenStream) -> Result<Vec<ASTNode>, Error> {
+    let mut nodes = Vec::new();
+
+    while !tokens.is_empty() {
+        nodes.push(parse_node(tokens)?);
+    }
 
     return Ok(nodes);
 }
 
-pub fn parse_node(tokens: &mut Peekable<std::vec::IntoIter<Token>>) -> Result<ASTNode, Error> {
+pub fn parse_node(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let span = tokens.span();
+
     if let Some(token) = tokens.next() {
         match token {
             Token::Number(number) => Ok(ASTNode::NumberLiteral(number)),
             Token::Boolean(value) => Ok(ASTNode::BooleanLiteral(value)),
+            Token::Nil => Ok(ASTNode::NilLiteral),
             Token::String(value) => Ok(ASTNode::StringLiteral(value)),
 
+            Token::Name(name) if name.len() > 1 && name.starts_with(':') => {
+                Ok(ASTNode::KeywordLiteral(name[1..].to_string()))
+            }
             Token::Name(name) => Ok(ASTNode::Identifier(name)),
 
             Token::NegativeSymbol => {
@@ -68,20 +692,42 @@ pub fn parse_node(tokens: &mut Peekable<std::vec::IntoIter<Token>>) -> Result<AS
 
                         Token::DefnKeyword => parse_function_declaration(tokens),
 
-                        Token::Name(_name) => parse_call_expression(tokens),
+                        Token::FnKeyword => parse_function_expression(tokens),
+
+                        Token::DefmethodKeyword => parse_method_declaration(tokens),
 
-                        _ => Err(Error::new(
-                            "Unexpected token",
+                        Token::ExtendTypeKeyword => parse_type_extension(tokens),
+
+                        Token::LetKeyword | Token::TryLetKeyword => parse_let_expression(tokens),
+
+                        Token::ClosingParenthesis => Err(Error::new(
+                            "Expected a function to call",
                             ErrorType::UnexpectedToken(token.clone()),
                         )),
+
+                        _ => parse_call_expression(tokens, span),
                     }
                 } else {
                     return Err(Error::new("Expected more tokens", ErrorType::MissingToken));
                 }
             }
 
+            Token::OpeningBrace => parse_map_expression(tokens),
+
+            // [a b] is shorthand for (list a b)
+            Token::OpeningBracket => parse_vector_expression(tokens),
+
+            // @value is shorthand for (deref value)
+            Token::AtSymbol => Ok(ASTNode::CallExpression(
+                Box::new(ASTNode::Identifier("deref".to_string())),
+                vec![parse_node(tokens)?],
+                span,
+            )),
+
+            Token::CaretSymbol => parse_metadata_expression(tokens),
+
             _ => Err(Error::new(
-                "Unexpected token",
+                "Expected an expression",
                 ErrorType::UnexpectedToken(token),
             )),
         }
@@ -90,24 +736,38 @@ pub fn parse_node(tokens: &mut Peekable<std::vec::IntoIter<Token>>) -> Result<AS
     }
 }
 
-fn parse_call_expression(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNode, Error> {
-    let identifier = {
-        if let Ok(ASTNode::Identifier(name)) = parse_node(tokens) {
-            name
-        } else {
+/// Parses the map and the expression following `^`. `^:name` is shorthand for
+/// `^{:name true}`.
+fn parse_metadata_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let metadata = match parse_node(tokens)? {
+        metadata @ ASTNode::MapExpression(_) => metadata,
+        ASTNode::KeywordLiteral(name) => ASTNode::MapExpression(vec![(
+            ASTNode::KeywordLiteral(name),
+            ASTNode::BooleanLiteral(true),
+        )]),
+        _ => {
             return Err(Error::new(
-                "Expected an identifier following '('",
-                ErrorType::UnexpectedToken(Token::OpeningParenthesis),
-            ));
+                "Expected a map or a keyword after ^",
+                ErrorType::UnexpectedToken(Token::CaretSymbol),
+            ))
         }
     };
 
+    return Ok(ASTNode::MetadataExpression {
+        metadata: Box::new(metadata),
+        expression: Box::new(parse_node(tokens)?),
+    });
+}
+
+fn parse_call_expression(tokens: &mut TokenStream, span: Span) -> Result<ASTNode, Error> {
+    let callee = parse_node(tokens)?;
+
     let mut arguments: Vec<ASTNode> = vec![];
 
     while let Some(next_token) = tokens.peek() {
         if *next_token == Token::ClosingParenthesis {
             tokens.next().unwrap();
-            return Ok(ASTNode::CallExpression(identifier, arguments));
+            return Ok(ASTNode::CallExpression(Box::new(callee), arguments, span));
         } else {
             arguments.push(parse_node(tokens)?);
         }
@@ -116,7 +776,7 @@ fn parse_call_expression(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNo
     return Err(Error::new("Expected missing ')'", ErrorType::MissingToken));
 }
 
-fn parse_if_expression(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNode, Error> {
+fn parse_if_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
     let token = tokens
         .next()
         .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
@@ -139,7 +799,7 @@ fn parse_if_expression(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNode
     return Ok(expression);
 }
 
-fn parse_list_expression(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNode, Error> {
+fn parse_list_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
     let token = tokens
         .next()
         .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
@@ -168,7 +828,7 @@ fn parse_list_expression(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNo
     return Ok(ASTNode::ListExpression(expressions));
 }
 
-fn parse_function_declaration(tokens: &mut Peekable<IntoIter<Token>>) -> Result<ASTNode, Error> {
+fn parse_function_declaration(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
     let token = tokens
         .next()
         .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
@@ -186,65 +846,587 @@ fn parse_function_declaration(tokens: &mut Peekable<IntoIter<Token>>) -> Result<
         }
     };
 
-    let mut parameter_list: Vec<String> = vec![];
+    let docstring = match tokens.peek() {
+        Some(Token::String(docstring)) => {
+            let docstring = Rc::from(docstring.as_str());
+            tokens.next().unwrap();
+            Some(docstring)
+        }
+        _ => None,
+    };
+
+    let arities = parse_function_arities(tokens)?;
 
-    {
-        let token = tokens
-            .next()
-            .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
-        assert_eq!(Token::OpeningBracket, token);
-    }
+    return Ok(ASTNode::FunctionDeclaration {
+        identifier,
+        docstring,
+        arities,
+    });
+}
 
-    while let Some(next_token) = tokens.next() {
-        match next_token {
-            Token::Name(name) => {
-                parameter_list.push(name);
-            }
+fn parse_method_declaration(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let token = tokens
+        .next()
+        .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
 
-            Token::ClosingBracket => break,
+    assert_eq!(Token::DefmethodKeyword, token);
 
-            _ => {
-                return Err(Error::new(
-                    "Unexpected token",
-                    ErrorType::UnexpectedToken(next_token),
-                ));
-            }
+    let identifier = match tokens.next() {
+        Some(Token::Name(name)) => name,
+        Some(token) => {
+            return Err(Error::new(
+                "Expected the name of a multimethod",
+                ErrorType::UnexpectedToken(token),
+            ))
         }
-    }
-
-    let body = parse_node(tokens)?;
+        None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    };
 
-    {
-        let token = tokens
-            .next()
-            .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
-        assert_eq!(Token::ClosingParenthesis, token);
-    }
+    let dispatch_value = Box::new(parse_node(tokens)?);
+    let arities = parse_function_arities(tokens)?;
 
-    return Ok(ASTNode::FunctionDeclaration {
+    return Ok(ASTNode::MethodDeclaration {
         identifier,
-        parameter_list,
-        body: Box::new(body),
+        dispatch_value,
+        arities,
     });
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn parse_type_extension(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let token = tokens
+        .next()
+        .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
 
-    #[test]
-    fn test_parsing_negative_number_literals() {
-        let mut tokens = vec![Token::NegativeSymbol, Token::Number(123)]
-            .into_iter()
-            .peekable();
+    assert_eq!(Token::ExtendTypeKeyword, token);
 
-        assert_eq!(parse_node(&mut tokens), Ok(ASTNode::NumberLiteral(-123)));
-        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    let mut names = Vec::with_capacity(2);
+
+    for expected in ["Expected a type name", "Expected a protocol name"] {
+        match tokens.next() {
+            Some(Token::Name(name)) => names.push(name),
+            Some(token) => return Err(Error::new(expected, ErrorType::UnexpectedToken(token))),
+            None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+        }
     }
 
-    #[test]
-    fn test_parsing_number_literal() {
-        let mut tokens = vec![Token::Number(123)].into_iter().peekable();
+    let protocol = names.pop().unwrap();
+    let type_name = names.pop().unwrap();
+    let mut methods = Vec::new();
+
+    loop {
+        match tokens.next() {
+            Some(Token::ClosingParenthesis) => break,
+
+            Some(Token::OpeningParenthesis) => match tokens.next() {
+                Some(Token::Name(name)) => methods.push((name, parse_function_arities(tokens)?)),
+                Some(token) => {
+                    return Err(Error::new(
+                        "Expected the name of a method",
+                        ErrorType::UnexpectedToken(token),
+                    ))
+                }
+                None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+            },
+
+            Some(token) => {
+                return Err(Error::new(
+                    "Expected a method implementation",
+                    ErrorType::UnexpectedToken(token),
+                ))
+            }
+
+            None => return Err(Error::new("Expected missing ')'", ErrorType::MissingToken)),
+        }
+    }
+
+    return Ok(ASTNode::TypeExtension {
+        type_name,
+        protocol,
+        methods,
+    });
+}
+
+fn parse_function_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let token = tokens
+        .next()
+        .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
+
+    assert_eq!(Token::FnKeyword, token);
+
+    let arities = parse_function_arities(tokens)?;
+
+    return Ok(ASTNode::FunctionExpression { arities });
+}
+
+/// Parses either a single `[params] body` or a list of `([params] body)`
+/// arities, followed by the closing parenthesis of the declaration.
+fn parse_function_arities(tokens: &mut TokenStream) -> Result<Vec<FunctionArity>, Error> {
+    let mut arities = Vec::new();
+
+    if tokens.peek() == Some(&Token::OpeningParenthesis) {
+        // Multi-arity declaration: (defn name ([a] ...) ([a b] ...))
+        while tokens.peek() == Some(&Token::OpeningParenthesis) {
+            tokens.next().unwrap();
+            arities.push(parse_function_arity(tokens)?);
+            expect_closing_parenthesis(tokens)?;
+        }
+    } else {
+        arities.push(parse_function_arity(tokens)?);
+    }
+
+    expect_closing_parenthesis(tokens)?;
+
+    return Ok(arities);
+}
+
+/// Consumes the `)` ending a function body, which has a single expression.
+fn expect_closing_parenthesis(tokens: &mut TokenStream) -> Result<(), Error> {
+    match tokens.next() {
+        Some(Token::ClosingParenthesis) => Ok(()),
+        Some(token) => Err(Error::new(
+            "Expected a closing parenthesis",
+            ErrorType::UnexpectedToken(token),
+        )),
+        None => Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    }
+}
+
+fn parse_function_arity(tokens: &mut TokenStream) -> Result<FunctionArity, Error> {
+    match tokens.next() {
+        Some(Token::OpeningBracket) => (),
+        Some(token) => {
+            return Err(Error::new(
+                "Expected a parameter list",
+                ErrorType::UnexpectedToken(token),
+            ))
+        }
+        None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    }
+
+    let parameter_list = parse_parameter_list(tokens)?;
+    let return_type = parse_type_annotation(tokens)?;
+    let mut body = parse_node(tokens)?;
+    let mut conditions = Conditions::default();
+
+    // A map followed by another expression holds conditions rather than
+    // being the body.
+    if let ASTNode::MapExpression(entries) = &body {
+        if tokens
+            .peek()
+            .is_some_and(|token| *token != Token::ClosingParenthesis)
+        {
+            conditions = parse_conditions(entries)?;
+            body = parse_node(tokens)?;
+        }
+    }
+
+    return Ok(FunctionArity {
+        parameter_list,
+        return_type,
+        conditions,
+        body,
+    });
+}
+
+/// Reads the entries of a `{:pre [...] :post [...]}` map.
+fn parse_conditions(entries: &[(ASTNode, ASTNode)]) -> Result<Conditions, Error> {
+    let mut conditions = Conditions::default();
+
+    for (key, value) in entries.iter() {
+        match (key, value) {
+            (ASTNode::KeywordLiteral(key), ASTNode::ListExpression(nodes)) if key == "pre" => {
+                conditions.pre = nodes.clone();
+            }
+            (ASTNode::KeywordLiteral(key), ASTNode::ListExpression(nodes)) if key == "post" => {
+                conditions.post = nodes.clone();
+            }
+            _ => {
+                return Err(Error::new(
+                    "Expected :pre and :post conditions",
+                    ErrorType::UnexpectedToken(Token::OpeningBrace),
+                ))
+            }
+        }
+    }
+
+    return Ok(conditions);
+}
+
+/// Parses `: Type` if it comes next.
+fn parse_type_annotation(tokens: &mut TokenStream) -> Result<Option<TypeAnnotation>, Error> {
+    if tokens.peek() != Some(&Token::Name(":".to_string())) {
+        return Ok(None);
+    }
+
+    let token = tokens.next().unwrap();
+    let span = tokens.span();
+
+    match tokens.next() {
+        Some(Token::Name(name)) => Ok(Some(TypeAnnotation { name, span })),
+        Some(_) => Err(Error::new(
+            "Expected a type following ':'",
+            ErrorType::UnexpectedToken(token),
+        )),
+        None => Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    }
+}
+
+/// Parses the inside of a `defn` parameter list once the opening bracket has
+/// been consumed, e.g. `[a [b c] &optional (d 1) &key (scale 2) & rest]`.
+fn parse_parameter_list(tokens: &mut TokenStream) -> Result<ParameterList, Error> {
+    enum Section {
+        Required,
+        Optional,
+        Keyword,
+    }
+
+    let mut parameter_list = ParameterList::default();
+    let mut section = Section::Required;
+    // The name of the last parameter, which a type annotation applies to.
+    let mut last_name: Option<String> = None;
+
+    loop {
+        match tokens.peek() {
+            Some(Token::Name(name)) if name == ":" => {
+                let token = tokens.peek().cloned().unwrap();
+                let annotation = parse_type_annotation(tokens)?.unwrap();
+
+                match last_name.take() {
+                    Some(name) => parameter_list.types.push((name, annotation)),
+                    None => {
+                        return Err(Error::new(
+                            "Expected a parameter name before ':'",
+                            ErrorType::UnexpectedToken(token),
+                        ))
+                    }
+                }
+            }
+
+            Some(Token::ClosingBracket) => {
+                tokens.next().unwrap();
+                return Ok(parameter_list);
+            }
+
+            Some(Token::Name(name)) if name == "&optional" => {
+                tokens.next().unwrap();
+                section = Section::Optional;
+                last_name = None;
+            }
+
+            Some(Token::Name(name)) if name == "&key" => {
+                tokens.next().unwrap();
+                section = Section::Keyword;
+                last_name = None;
+            }
+
+            Some(Token::Name(name)) if name == "&" => {
+                let token = tokens.next().unwrap();
+                let rest = parse_pattern(tokens)?;
+
+                if let Pattern::Identifier(name) = &rest {
+                    if let Some(annotation) = parse_type_annotation(tokens)? {
+                        parameter_list.types.push((name.to_string(), annotation));
+                    }
+                }
+
+                parameter_list.rest = Some(rest);
+
+                if tokens.peek() != Some(&Token::ClosingBracket) {
+                    return Err(Error::new(
+                        "Expected ']' after the rest parameter",
+                        ErrorType::UnexpectedToken(token),
+                    ));
+                }
+            }
+
+            Some(_) => match section {
+                Section::Required => {
+                    let pattern = parse_pattern(tokens)?;
+
+                    last_name = match &pattern {
+                        Pattern::Identifier(name) => Some(name.to_string()),
+                        _ => None,
+                    };
+                    parameter_list.required.push(pattern);
+                }
+
+                Section::Optional => {
+                    let parameter = parse_defaulted_parameter(tokens)?;
+                    last_name = Some(parameter.0.clone());
+                    parameter_list.optional.push(parameter);
+                }
+
+                Section::Keyword => {
+                    let parameter = parse_defaulted_parameter(tokens)?;
+                    last_name = Some(parameter.0.clone());
+                    parameter_list.keywords.push(parameter);
+                }
+            },
+
+            None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+        }
+    }
+}
+
+/// Parses either `name` or `(name default)`.
+fn parse_defaulted_parameter(tokens: &mut TokenStream) -> Result<(String, Option<ASTNode>), Error> {
+    match tokens.next() {
+        Some(Token::Name(name)) => Ok((name, None)),
+
+        Some(Token::OpeningParenthesis) => {
+            let name = match tokens.next() {
+                Some(Token::Name(name)) => name,
+                Some(token) => {
+                    return Err(Error::new(
+                        "Expected a parameter name",
+                        ErrorType::UnexpectedToken(token),
+                    ))
+                }
+                None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+            };
+
+            let default = parse_node(tokens)?;
+
+            match tokens.next() {
+                Some(Token::ClosingParenthesis) => Ok((name, Some(default))),
+                Some(token) => Err(Error::new(
+                    "Expected ')' after the default value",
+                    ErrorType::UnexpectedToken(token),
+                )),
+                None => Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+            }
+        }
+
+        Some(token) => Err(Error::new(
+            "Expected a parameter name or (name default)",
+            ErrorType::UnexpectedToken(token),
+        )),
+
+        None => Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    }
+}
+
+fn parse_map_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let mut entries = Vec::new();
+
+    while let Some(next_token) = tokens.peek() {
+        if *next_token == Token::ClosingBrace {
+            tokens.next().unwrap();
+            return Ok(ASTNode::MapExpression(entries));
+        }
+
+        let key = parse_node(tokens)?;
+
+        if tokens.peek() == Some(&Token::ClosingBrace) {
+            return Err(Error::new(
+                "Expected a value for every key in a map",
+                ErrorType::UnexpectedToken(Token::ClosingBrace),
+            ));
+        }
+
+        entries.push((key, parse_node(tokens)?));
+    }
+
+    return Err(Error::new("Expected missing '}'", ErrorType::MissingToken));
+}
+
+fn parse_vector_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let mut expressions = Vec::new();
+
+    while let Some(next_token) = tokens.peek() {
+        if *next_token == Token::ClosingBracket {
+            tokens.next().unwrap();
+            return Ok(ASTNode::ListExpression(expressions));
+        }
+
+        expressions.push(parse_node(tokens)?);
+    }
+
+    return Err(Error::new("Expected missing ']'", ErrorType::MissingToken));
+}
+
+fn parse_let_expression(tokens: &mut TokenStream) -> Result<ASTNode, Error> {
+    let token = tokens
+        .next()
+        .ok_or(Error::new("Missing tokens", ErrorType::MissingToken))?;
+
+    assert!(matches!(token, Token::LetKeyword | Token::TryLetKeyword));
+
+    match tokens.next() {
+        Some(Token::OpeningBracket) => (),
+        Some(next_token) => {
+            return Err(Error::new(
+                "Expected a binding vector following let keyword",
+                ErrorType::UnexpectedToken(next_token),
+            ))
+        }
+        None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    }
+
+    let mut bindings = Vec::new();
+
+    loop {
+        match tokens.peek() {
+            Some(Token::ClosingBracket) => {
+                tokens.next().unwrap();
+                break;
+            }
+
+            Some(_) => {
+                let pattern = parse_pattern(tokens)?;
+
+                if tokens.peek() == Some(&Token::ClosingBracket) {
+                    return Err(Error::new(
+                        "Expected a value for every binding in let",
+                        ErrorType::UnexpectedToken(Token::ClosingBracket),
+                    ));
+                }
+
+                bindings.push((pattern, parse_node(tokens)?));
+            }
+
+            None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+        }
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(next_token) = tokens.peek() {
+        if *next_token == Token::ClosingParenthesis {
+            tokens.next().unwrap();
+
+            if token == Token::TryLetKeyword {
+                return Ok(ASTNode::TryLetExpression { bindings, body });
+            }
+
+            return Ok(ASTNode::LetExpression { bindings, body });
+        } else {
+            body.push(parse_node(tokens)?);
+        }
+    }
+
+    return Err(Error::new("Expected missing ')'", ErrorType::MissingToken));
+}
+
+fn parse_pattern(tokens: &mut TokenStream) -> Result<Pattern, Error> {
+    match tokens.next() {
+        Some(Token::Name(name)) if name != "&" => Ok(Pattern::Identifier(name)),
+        Some(Token::OpeningBracket) => parse_sequence_pattern(tokens),
+        Some(Token::OpeningBrace) => parse_map_pattern(tokens),
+        Some(token) => Err(Error::new(
+            "Expected a name, '[' or '{' in binding",
+            ErrorType::UnexpectedToken(token),
+        )),
+        None => Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+    }
+}
+
+/// Parses the inside of `[...]` once the opening bracket has been consumed.
+fn parse_sequence_pattern(tokens: &mut TokenStream) -> Result<Pattern, Error> {
+    let mut elements = Vec::new();
+    let mut rest = None;
+
+    loop {
+        match tokens.peek() {
+            Some(Token::ClosingBracket) => {
+                tokens.next().unwrap();
+                return Ok(Pattern::Sequence { elements, rest });
+            }
+
+            Some(Token::Name(name)) if name == "&" => {
+                let token = tokens.next().unwrap();
+
+                if rest.is_some() {
+                    return Err(Error::new(
+                        "Only one '&' is allowed in a binding",
+                        ErrorType::UnexpectedToken(token),
+                    ));
+                }
+
+                rest = Some(Box::new(parse_pattern(tokens)?));
+
+                match tokens.peek() {
+                    Some(Token::ClosingBracket) => (),
+                    Some(unexpected) => {
+                        return Err(Error::new(
+                            "Expected ']' after the rest binding",
+                            ErrorType::UnexpectedToken(unexpected.clone()),
+                        ));
+                    }
+                    None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+                }
+            }
+
+            Some(_) => elements.push(parse_pattern(tokens)?),
+
+            None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+        }
+    }
+}
+
+/// Parses the inside of `{...}` once the opening brace has been consumed.
+fn parse_map_pattern(tokens: &mut TokenStream) -> Result<Pattern, Error> {
+    let mut keys = Vec::new();
+
+    loop {
+        match tokens.next() {
+            Some(Token::ClosingBrace) => return Ok(Pattern::Map { keys }),
+
+            Some(Token::Name(name)) if name == ":keys" => {
+                match tokens.next() {
+                    Some(Token::OpeningBracket) => (),
+                    Some(token) => {
+                        return Err(Error::new(
+                            "Expected '[' following :keys",
+                            ErrorType::UnexpectedToken(token),
+                        ))
+                    }
+                    None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+                }
+
+                loop {
+                    match tokens.next() {
+                        Some(Token::ClosingBracket) => break,
+                        Some(Token::Name(name)) => keys.push(name),
+                        Some(token) => {
+                            return Err(Error::new(
+                                "Expected a name in :keys",
+                                ErrorType::UnexpectedToken(token),
+                            ))
+                        }
+                        None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+                    }
+                }
+            }
+
+            Some(token) => {
+                return Err(Error::new(
+                    "Expected :keys in map binding",
+                    ErrorType::UnexpectedToken(token),
+                ))
+            }
+
+            None => return Err(Error::new("Missing tokens", ErrorType::MissingToken)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tokenize, tokenizer::tokenize_with_spans};
+
+    #[test]
+    fn test_parsing_negative_number_literals() {
+        let mut tokens: TokenStream = vec![Token::NegativeSymbol, Token::Number(123)].into();
+
+        assert_eq!(parse_node(&mut tokens), Ok(ASTNode::NumberLiteral(-123)));
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_number_literal() {
+        let mut tokens: TokenStream = vec![Token::Number(123)].into();
 
         assert_eq!(parse_node(&mut tokens), Ok(ASTNode::NumberLiteral(123)));
         assert_eq!(tokens.peek(), None, "Has left-over tokens");
@@ -252,9 +1434,7 @@ mod tests {
 
     #[test]
     fn test_parsing_identifier() {
-        let mut tokens = vec![Token::Name("hello-there".to_string())]
-            .into_iter()
-            .peekable();
+        let mut tokens: TokenStream = vec![Token::Name("hello-there".to_string())].into();
 
         assert_eq!(
             parse_node(&mut tokens),
@@ -265,42 +1445,89 @@ mod tests {
 
     #[test]
     fn test_parsing_call_expression() {
-        let mut tokens = vec![
+        let mut tokens: TokenStream = vec![
             Token::OpeningParenthesis,
             Token::Name("hello-there".to_string()),
             Token::Number(123),
             Token::ClosingParenthesis,
         ]
-        .into_iter()
-        .peekable();
+        .into();
 
         assert_eq!(
             parse_node(&mut tokens),
             Ok(ASTNode::CallExpression(
-                "hello-there".to_string(),
-                vec![ASTNode::NumberLiteral(123)]
+                Box::new(ASTNode::Identifier("hello-there".to_string())),
+                vec![ASTNode::NumberLiteral(123)],
+                Span::default()
             ))
         );
         assert_eq!(tokens.peek(), None, "Has left-over tokens");
     }
 
+    #[test]
+    fn test_parsing_call_expression_with_expression_callee() {
+        let mut tokens: TokenStream = tokenize_with_spans("((make-adder 1) 2)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::CallExpression(
+                Box::new(ASTNode::CallExpression(
+                    Box::new(ASTNode::Identifier("make-adder".to_string())),
+                    vec![ASTNode::NumberLiteral(1)],
+                    Span { line: 1, column: 2 }
+                )),
+                vec![ASTNode::NumberLiteral(2)],
+                Span { line: 1, column: 1 }
+            ))
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_function_expression() {
+        let mut tokens: TokenStream = tokenize("(fn [a] a)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::FunctionExpression {
+                arities: vec![FunctionArity {
+                    parameter_list: ParameterList {
+                        required: vec![Pattern::Identifier("a".to_string())],
+                        ..ParameterList::default()
+                    },
+                    return_type: None,
+                    conditions: Conditions::default(),
+                    body: ASTNode::Identifier("a".to_string()),
+                }],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
     #[test]
     fn test_parsing_true_literals() {
-        let mut tokens = vec![Token::Boolean(true)].into_iter().peekable();
+        let mut tokens: TokenStream = vec![Token::Boolean(true)].into();
         assert_eq!(parse_node(&mut tokens), Ok(ASTNode::BooleanLiteral(true)));
         assert_eq!(tokens.peek(), None, "Has left-over tokens");
     }
 
     #[test]
     fn test_parsing_false_literals() {
-        let mut tokens = vec![Token::Boolean(false)].into_iter().peekable();
+        let mut tokens: TokenStream = vec![Token::Boolean(false)].into();
         assert_eq!(parse_node(&mut tokens), Ok(ASTNode::BooleanLiteral(false)));
         assert_eq!(tokens.peek(), None, "Has left-over tokens");
     }
 
+    #[test]
+    fn test_parsing_nil_literals() {
+        let mut tokens: TokenStream = vec![Token::Nil].into();
+        assert_eq!(parse_node(&mut tokens), Ok(ASTNode::NilLiteral));
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
     #[test]
     fn test_parsing_function_declaration() {
-        let mut tokens = vec![
+        let mut tokens: TokenStream = vec![
             Token::OpeningParenthesis,
             Token::DefnKeyword,
             Token::Name("hello-there".to_string()),
@@ -310,31 +1537,396 @@ mod tests {
             Token::Number(123),
             Token::ClosingParenthesis,
         ]
-        .into_iter()
-        .peekable();
+        .into();
 
         assert_eq!(
             parse_node(&mut tokens),
             Ok(ASTNode::FunctionDeclaration {
                 identifier: "hello-there".to_string(),
-                parameter_list: vec!["a".to_string()],
-                body: Box::new(ASTNode::NumberLiteral(123)),
+                docstring: None,
+                arities: vec![FunctionArity {
+                    parameter_list: ParameterList {
+                        required: vec![Pattern::Identifier("a".to_string())],
+                        ..ParameterList::default()
+                    },
+                    return_type: None,
+                    conditions: Conditions::default(),
+                    body: ASTNode::NumberLiteral(123),
+                }],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_destructuring_parameter_list() {
+        let mut tokens: TokenStream = tokenize("(defn first-two [[a b] & rest] a)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::FunctionDeclaration {
+                identifier: "first-two".to_string(),
+                docstring: None,
+                arities: vec![FunctionArity {
+                    parameter_list: ParameterList {
+                        required: vec![Pattern::Sequence {
+                            elements: vec![
+                                Pattern::Identifier("a".to_string()),
+                                Pattern::Identifier("b".to_string()),
+                            ],
+                            rest: None,
+                        }],
+                        rest: Some(Pattern::Identifier("rest".to_string())),
+                        ..ParameterList::default()
+                    },
+                    return_type: None,
+                    conditions: Conditions::default(),
+                    body: ASTNode::Identifier("a".to_string()),
+                }],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_functions_with_several_body_expressions() {
+        for source in ["(defn f ([a] a b))", "(defn f [a] a b)", "(fn [a] a b)"] {
+            let mut tokens: TokenStream = tokenize(source).into();
+
+            assert_eq!(
+                parse_node(&mut tokens),
+                Err(Error::new(
+                    "Expected a closing parenthesis",
+                    ErrorType::UnexpectedToken(Token::Name("b".to_string()))
+                )),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_parsing_misplaced_rest_binding() {
+        let mut tokens: TokenStream = tokenize("(let [[a & rest b] xs] a)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens).unwrap_err().error_type(),
+            &ErrorType::UnexpectedToken(Token::Name("b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parsing_type_annotations() {
+        let mut tokens: TokenStream =
+            tokenize_with_spans("(defn f [a : Number [b] &optional c : String] : List a)").into();
+
+        let Ok(ASTNode::FunctionDeclaration { arities, .. }) = parse_node(&mut tokens) else {
+            panic!("Expected a function declaration");
+        };
+
+        assert_eq!(
+            arities[0].parameter_list.types,
+            vec![
+                (
+                    "a".to_string(),
+                    TypeAnnotation {
+                        name: "Number".to_string(),
+                        span: Span {
+                            line: 1,
+                            column: 14
+                        }
+                    }
+                ),
+                (
+                    "c".to_string(),
+                    TypeAnnotation {
+                        name: "String".to_string(),
+                        span: Span {
+                            line: 1,
+                            column: 39
+                        }
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            arities[0].return_type,
+            Some(TypeAnnotation {
+                name: "List".to_string(),
+                span: Span {
+                    line: 1,
+                    column: 49
+                }
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+
+        let mut tokens: TokenStream = tokenize("(defn f [[a] : List] a)").into();
+        assert!(parse_node(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_parsing_conditions() {
+        let mut tokens: TokenStream =
+            tokenize("(defn f [a] {:pre [(is-greater a 0)] :post [%]} {\"a\" a})").into();
+
+        let Ok(ASTNode::FunctionDeclaration { arities, .. }) = parse_node(&mut tokens) else {
+            panic!("Expected a function declaration");
+        };
+
+        assert_eq!(
+            arities[0].conditions,
+            Conditions {
+                pre: vec![ASTNode::CallExpression(
+                    Box::new(ASTNode::Identifier("is-greater".to_string())),
+                    vec![
+                        ASTNode::Identifier("a".to_string()),
+                        ASTNode::NumberLiteral(0)
+                    ],
+                    Span::default(),
+                )],
+                post: vec![ASTNode::Identifier("%".to_string())],
+            }
+        );
+        assert_eq!(
+            arities[0].body,
+            ASTNode::MapExpression(vec![(
+                ASTNode::StringLiteral("a".to_string()),
+                ASTNode::Identifier("a".to_string())
+            )])
+        );
+
+        let mut tokens: TokenStream = tokenize("(defn f [a] {:before [a]} a)").into();
+        assert!(parse_node(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_displaying_nodes() {
+        let source = "(defn f ([] 0) ([a : Number [b c] &optional (d 1) & more] : List {:pre [(is-greater a 0)]} (let [{:keys [x]} d] (if x [a :b \"c\"] {:d nil}))))";
+        let mut tokens: TokenStream = tokenize(source).into();
+
+        assert_eq!(parse_node(&mut tokens).unwrap().to_string(), source);
+    }
+
+    #[test]
+    fn test_parsing_metadata() {
+        let mut tokens: TokenStream = tokenize("^:private (define x 1)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::MetadataExpression {
+                metadata: Box::new(ASTNode::MapExpression(vec![(
+                    ASTNode::KeywordLiteral("private".to_string()),
+                    ASTNode::BooleanLiteral(true)
+                )])),
+                expression: Box::new(ASTNode::CallExpression(
+                    Box::new(ASTNode::Identifier("define".to_string())),
+                    vec![
+                        ASTNode::Identifier("x".to_string()),
+                        ASTNode::NumberLiteral(1)
+                    ],
+                    Span::default(),
+                )),
+            })
+        );
+
+        let source = "^{:deprecated \"use g\"} (defn f [] 1)";
+        let mut tokens: TokenStream = tokenize(source).into();
+        assert_eq!(parse_node(&mut tokens).unwrap().to_string(), source);
+
+        let mut tokens: TokenStream = tokenize("^1 (define x 1)").into();
+        assert!(parse_node(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_parsing_docstrings() {
+        let mut tokens: TokenStream = tokenize("(defn id \"Returns x.\" [x] x)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::FunctionDeclaration {
+                identifier: "id".to_string(),
+                docstring: Some(Rc::from("Returns x.")),
+                arities: vec![FunctionArity {
+                    parameter_list: ParameterList {
+                        required: vec![Pattern::Identifier("x".to_string())],
+                        ..ParameterList::default()
+                    },
+                    return_type: None,
+                    conditions: Conditions::default(),
+                    body: ASTNode::Identifier("x".to_string()),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_pretty_printing() {
+        let source = "(defn describe \"Describes a shape by its kind and size.\" ([shape] (describe shape 1)) ([shape scale] (let [kind (get shape \"kind\") size (multiply scale (get shape \"size\"))] (str kind \" of size \" size))))";
+        let mut tokens: TokenStream = tokenize(source).into();
+
+        assert_eq!(
+            parse_node(&mut tokens).unwrap().to_pretty_string(),
+            "(defn describe
+  \"Describes a shape by its kind and size.\"
+  ([shape] (describe shape 1))
+  ([shape scale]
+   (let [kind (get shape \"kind\")
+         size (multiply scale (get shape \"size\"))]
+     (str kind \" of size \" size))))"
+        );
+
+        let mut tokens: TokenStream = tokenize("(defn inc [n] (add n 1))").into();
+        assert_eq!(
+            parse_node(&mut tokens).unwrap().to_pretty_string(),
+            "(defn inc [n] (add n 1))"
+        );
+    }
+
+    #[test]
+    fn test_parsing_optional_and_keyword_parameters() {
+        let mut tokens: TokenStream =
+            tokenize("(defn f [a &optional b (c 1) &key (scale 2)] a)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::FunctionDeclaration {
+                identifier: "f".to_string(),
+                docstring: None,
+                arities: vec![FunctionArity {
+                    parameter_list: ParameterList {
+                        required: vec![Pattern::Identifier("a".to_string())],
+                        optional: vec![
+                            ("b".to_string(), None),
+                            ("c".to_string(), Some(ASTNode::NumberLiteral(1))),
+                        ],
+                        keywords: vec![("scale".to_string(), Some(ASTNode::NumberLiteral(2)))],
+                        rest: None,
+                        types: Vec::new(),
+                    },
+                    return_type: None,
+                    conditions: Conditions::default(),
+                    body: ASTNode::Identifier("a".to_string()),
+                }],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_multi_arity_function_declaration() {
+        let mut tokens: TokenStream = tokenize("(defn f ([] 0) ([a] a))").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::FunctionDeclaration {
+                identifier: "f".to_string(),
+                docstring: None,
+                arities: vec![
+                    FunctionArity {
+                        parameter_list: ParameterList::default(),
+                        return_type: None,
+                        conditions: Conditions::default(),
+                        body: ASTNode::NumberLiteral(0),
+                    },
+                    FunctionArity {
+                        parameter_list: ParameterList {
+                            required: vec![Pattern::Identifier("a".to_string())],
+                            ..ParameterList::default()
+                        },
+                        return_type: None,
+                        conditions: Conditions::default(),
+                        body: ASTNode::Identifier("a".to_string()),
+                    },
+                ],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_try_let_expression() {
+        let mut tokens: TokenStream = tokenize("(try? [a (ok 1)] a)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::TryLetExpression {
+                bindings: vec![(
+                    Pattern::Identifier("a".to_string()),
+                    ASTNode::CallExpression(
+                        Box::new(ASTNode::Identifier("ok".to_string())),
+                        vec![ASTNode::NumberLiteral(1)],
+                        Span::default()
+                    )
+                )],
+                body: vec![ASTNode::Identifier("a".to_string())],
             })
         );
         assert_eq!(tokens.peek(), None, "Has left-over tokens");
     }
 
+    #[test]
+    fn test_parsing_let_expression() {
+        let mut tokens: TokenStream = tokenize("(let [{:keys [x y]} point z 1] x)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::LetExpression {
+                bindings: vec![
+                    (
+                        Pattern::Map {
+                            keys: vec!["x".to_string(), "y".to_string()]
+                        },
+                        ASTNode::Identifier("point".to_string())
+                    ),
+                    (
+                        Pattern::Identifier("z".to_string()),
+                        ASTNode::NumberLiteral(1)
+                    ),
+                ],
+                body: vec![ASTNode::Identifier("x".to_string())],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_invalid_rest_binding() {
+        let mut tokens: TokenStream = tokenize("(defn f [& a b] a)").into();
+
+        assert!(parse_node(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_parsing_map_expression() {
+        let mut tokens: TokenStream = tokenize(r#"{"x" 1 "y" 2}"#).into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::MapExpression(vec![
+                (
+                    ASTNode::StringLiteral("x".to_string()),
+                    ASTNode::NumberLiteral(1)
+                ),
+                (
+                    ASTNode::StringLiteral("y".to_string()),
+                    ASTNode::NumberLiteral(2)
+                ),
+            ]))
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
     #[test]
     fn test_parsing_list_expression() {
-        let mut tokens = vec![
+        let mut tokens: TokenStream = vec![
             Token::OpeningParenthesis,
             Token::ListKeyword,
             Token::String("a".to_string()),
             Token::Number(123),
             Token::ClosingParenthesis,
         ]
-        .into_iter()
-        .peekable();
+        .into();
 
         assert_eq!(
             parse_node(&mut tokens),
@@ -346,9 +1938,85 @@ mod tests {
         assert_eq!(tokens.peek(), None, "Has left-over tokens");
     }
 
+    #[test]
+    fn test_parsing_method_declaration() {
+        let mut tokens: TokenStream = tokenize("(defmethod area \"circle\" [c] c)").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::MethodDeclaration {
+                identifier: "area".to_string(),
+                dispatch_value: Box::new(ASTNode::StringLiteral("circle".to_string())),
+                arities: vec![FunctionArity {
+                    parameter_list: ParameterList {
+                        required: vec![Pattern::Identifier("c".to_string())],
+                        ..ParameterList::default()
+                    },
+                    return_type: None,
+                    conditions: Conditions::default(),
+                    body: ASTNode::Identifier("c".to_string()),
+                }],
+            })
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_type_extension() {
+        let mut tokens: TokenStream =
+            tokenize("(extend-type String Shape (area [s] 0) (name ([] 1) ([a] a)))").into();
+
+        let ASTNode::TypeExtension {
+            type_name,
+            protocol,
+            methods,
+        } = parse_node(&mut tokens).unwrap()
+        else {
+            panic!("Expected a type extension");
+        };
+
+        assert_eq!((type_name.as_str(), protocol.as_str()), ("String", "Shape"));
+        assert_eq!(
+            methods
+                .iter()
+                .map(|(name, arities)| (name.as_str(), arities.len()))
+                .collect::<Vec<_>>(),
+            vec![("area", 1), ("name", 2)]
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_vector_expression() {
+        let mut tokens: TokenStream = tokenize("[a 1]").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::ListExpression(vec![
+                ASTNode::Identifier("a".to_string()),
+                ASTNode::NumberLiteral(1),
+            ]))
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
+    #[test]
+    fn test_parsing_keywords() {
+        let mut tokens: TokenStream = tokenize("{:name 1}").into();
+
+        assert_eq!(
+            parse_node(&mut tokens),
+            Ok(ASTNode::MapExpression(vec![(
+                ASTNode::KeywordLiteral("name".to_string()),
+                ASTNode::NumberLiteral(1),
+            )]))
+        );
+        assert_eq!(tokens.peek(), None, "Has left-over tokens");
+    }
+
     #[test]
     fn test_parsing_if_else_expression() {
-        let mut tokens = &mut vec![
+        let mut tokens: TokenStream = vec![
             Token::OpeningParenthesis,
             Token::IfKeyword,
             Token::Boolean(true),
@@ -356,8 +2024,7 @@ mod tests {
             Token::Number(123),
             Token::ClosingParenthesis,
         ]
-        .into_iter()
-        .peekable();
+        .into();
 
         assert_eq!(
             parse_node(&mut tokens),
@@ -372,9 +2039,8 @@ mod tests {
 
     #[test]
     fn test_parsing_multiple_expressions() {
-        let mut tokens = vec![Token::Boolean(true), Token::Number(321), Token::Number(123)]
-            .into_iter()
-            .peekable();
+        let mut tokens: TokenStream =
+            vec![Token::Boolean(true), Token::Number(321), Token::Number(123)].into();
 
         assert_eq!(
             parse(&mut tokens),
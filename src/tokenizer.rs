@@ -1,26 +1,126 @@
+use std::{iter::Peekable, str::Chars};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     OpeningParenthesis,
     ClosingParenthesis,
     NegativeSymbol,
+    AtSymbol,
+    CaretSymbol,
     OpeningBracket,
     ClosingBracket,
+    OpeningBrace,
+    ClosingBrace,
 
     IfKeyword,
     DefnKeyword,
+    FnKeyword,
     ListKeyword,
+    LetKeyword,
+    TryLetKeyword,
+    DefmethodKeyword,
+    ExtendTypeKeyword,
 
     String(String),
     Boolean(bool),
+    Nil,
     Number(i64),
     Name(String),
 }
 
+/// Writes the token the way it appears in the source.
+impl std::fmt::Display for Token {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::OpeningParenthesis => write!(formatter, "("),
+            Token::ClosingParenthesis => write!(formatter, ")"),
+            Token::NegativeSymbol => write!(formatter, "-"),
+            Token::AtSymbol => write!(formatter, "@"),
+            Token::CaretSymbol => write!(formatter, "^"),
+            Token::OpeningBracket => write!(formatter, "["),
+            Token::ClosingBracket => write!(formatter, "]"),
+            Token::OpeningBrace => write!(formatter, "{{"),
+            Token::ClosingBrace => write!(formatter, "}}"),
+            Token::IfKeyword => write!(formatter, "if"),
+            Token::DefnKeyword => write!(formatter, "defn"),
+            Token::FnKeyword => write!(formatter, "fn"),
+            Token::ListKeyword => write!(formatter, "list"),
+            Token::LetKeyword => write!(formatter, "let"),
+            Token::TryLetKeyword => write!(formatter, "try?"),
+            Token::DefmethodKeyword => write!(formatter, "defmethod"),
+            Token::ExtendTypeKeyword => write!(formatter, "extend-type"),
+            Token::String(string) => write!(formatter, "{:?}", string),
+            Token::Boolean(value) => write!(formatter, "{}", value),
+            Token::Nil => write!(formatter, "nil"),
+            Token::Number(number) => write!(formatter, "{}", number),
+            Token::Name(name) => write!(formatter, "{}", name),
+        }
+    }
+}
+
+/// The position a token starts at in the source, counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Iterates over the characters of the source, keeping track of the
+/// position of the next one.
+struct Cursor<'a> {
+    characters: Peekable<Chars<'a>>,
+    span: Span,
+}
+
+impl Cursor<'_> {
+    fn peek(&mut self) -> Option<&char> {
+        self.characters.peek()
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let character = self.characters.next()?;
+
+        if character == '\n' {
+            self.span.line += 1;
+            self.span.column = 1;
+        } else {
+            self.span.column += 1;
+        }
+
+        return Some(character);
+    }
+}
+
 pub fn tokenize(input: &str) -> Vec<Token> {
-    let mut cursor = input.chars().peekable();
+    return tokenize_with_spans(input)
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+}
+
+/// Tokenizes `input`, pairing every token with the position it starts at.
+pub fn tokenize_with_spans(input: &str) -> Vec<(Token, Span)> {
+    let mut cursor = Cursor {
+        characters: input.chars().peekable(),
+        span: Span { line: 1, column: 1 },
+    };
     let mut tokens = vec![];
+    let mut spans = vec![];
+    let mut span = cursor.span;
+
+    loop {
+        // Every token pushed while handling a character starts at it.
+        spans.resize(tokens.len(), span);
+        span = cursor.span;
+
+        let Some(character) = cursor.next() else {
+            break;
+        };
 
-    while let Some(character) = cursor.next() {
         if character.is_whitespace() {
             continue;
         }
@@ -61,7 +161,7 @@ pub fn tokenize(input: &str) -> Vec<Token> {
             '"' => {
                 let mut value_string = String::new();
 
-                while let Some(next_character) = cursor.next() {
+                for next_character in cursor.by_ref() {
                     if next_character != '"' {
                         value_string.push(next_character);
                     } else {
@@ -80,10 +180,26 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 tokens.push(Token::ClosingBracket);
             }
 
+            '{' => {
+                tokens.push(Token::OpeningBrace);
+            }
+
+            '}' => {
+                tokens.push(Token::ClosingBrace);
+            }
+
             '-' => {
                 tokens.push(Token::NegativeSymbol);
             }
 
+            '@' => {
+                tokens.push(Token::AtSymbol);
+            }
+
+            '^' => {
+                tokens.push(Token::CaretSymbol);
+            }
+
             _ => {
                 let mut name = String::from(character);
 
@@ -91,6 +207,7 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                     if !next_character.is_whitespace()
                         && *next_character != ')'
                         && *next_character != ']'
+                        && *next_character != '}'
                     {
                         name.push(cursor.next().unwrap());
                     } else {
@@ -101,16 +218,22 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 match &name[..] {
                     "if" => tokens.push(Token::IfKeyword),
                     "defn" => tokens.push(Token::DefnKeyword),
+                    "fn" => tokens.push(Token::FnKeyword),
                     "list" => tokens.push(Token::ListKeyword),
+                    "let" => tokens.push(Token::LetKeyword),
+                    "try?" => tokens.push(Token::TryLetKeyword),
+                    "defmethod" => tokens.push(Token::DefmethodKeyword),
+                    "extend-type" => tokens.push(Token::ExtendTypeKeyword),
                     "true" => tokens.push(Token::Boolean(true)),
                     "false" => tokens.push(Token::Boolean(false)),
+                    "nil" => tokens.push(Token::Nil),
                     _ => tokens.push(Token::Name(name)),
                 }
             }
         }
     }
 
-    return tokens;
+    return tokens.into_iter().zip(spans).collect();
 }
 
 #[cfg(test)]
@@ -141,6 +264,45 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parsing_destructuring_patterns() {
+        assert_eq!(
+            tokenize("[[a b] & rest] {:keys [x]}"),
+            vec![
+                Token::OpeningBracket,
+                Token::OpeningBracket,
+                Token::Name("a".to_string()),
+                Token::Name("b".to_string()),
+                Token::ClosingBracket,
+                Token::Name("&".to_string()),
+                Token::Name("rest".to_string()),
+                Token::ClosingBracket,
+                Token::OpeningBrace,
+                Token::Name(":keys".to_string()),
+                Token::OpeningBracket,
+                Token::Name("x".to_string()),
+                Token::ClosingBracket,
+                Token::ClosingBrace,
+            ]
+        )
+    }
+
+    #[test]
+    fn test_parsing_deref_shorthand() {
+        assert_eq!(
+            tokenize("@counter"),
+            vec![Token::AtSymbol, Token::Name("counter".to_string())]
+        )
+    }
+
+    #[test]
+    fn test_parsing_metadata_shorthand() {
+        assert_eq!(
+            tokenize("^:private"),
+            vec![Token::CaretSymbol, Token::Name(":private".to_string())]
+        )
+    }
+
     #[test]
     fn test_parsing_numbers() {
         assert_eq!(
@@ -195,6 +357,28 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_token_spans() {
+        assert_eq!(
+            tokenize_with_spans("(add 1\n  \"a b\")"),
+            vec![
+                (Token::OpeningParenthesis, Span { line: 1, column: 1 }),
+                (Token::Name("add".to_string()), Span { line: 1, column: 2 }),
+                (Token::Number(1), Span { line: 1, column: 6 }),
+                (
+                    Token::String("a b".to_string()),
+                    Span { line: 2, column: 3 }
+                ),
+                (Token::ClosingParenthesis, Span { line: 2, column: 8 }),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_nil_literal() {
+        assert_eq!(tokenize("nil"), vec![Token::Nil])
+    }
+
     #[test]
     fn test_ignore_whitespace() {
         assert_eq!(tokenize("               "), vec![],)
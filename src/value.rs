@@ -1,21 +1,60 @@
-use crate::ASTNode;
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+    path::Path,
+    rc::Rc,
+};
 
-#[derive(Debug, PartialEq)]
+use crate::{interpreter::environment::Closure, Error, FunctionArity, Interpreter};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Number,
     String,
     List,
+    LazySequence,
+    Map,
     Boolean,
     Function,
+    Promise,
+    Atom,
+    Result,
+    Keyword,
+    /// A record type declared with `defrecord`, by name.
+    Record(String),
     Nil,
 }
 
+/// The name of the type as risp programs write it, e.g. `Number` or `Point`.
+impl std::fmt::Display for Type {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Record(name) => write!(formatter, "{}", name),
+            value_type => write!(formatter, "{:?}", value_type),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Number(i64),
     String(String),
-    List(Vec<Value>),
+    List(List),
+    LazySequence(LazySequence),
+    Map(Map),
     Function(Function),
+    Builtin(Builtin),
+    MultiMethod(MultiMethod),
+    Promise(Promise),
+    Atom(Atom),
+    /// The success case of a result value.
+    Ok(Box<Value>),
+    /// The failure case of a result value.
+    Err(Box<Value>),
+    Record(Record),
+    Keyword(Keyword),
     Boolean(bool),
     Nil,
 }
@@ -25,18 +64,25 @@ impl Value {
         match self {
             Value::Number(number) => number > &0,
             Value::Boolean(value) => *value,
-            Value::String(value) => value.len() > 0,
+            Value::String(value) => !value.is_empty(),
             Value::List(value) => !value.is_empty(),
+            Value::LazySequence(_) => true,
+            Value::Map(map) => !map.is_empty(),
             Value::Function(_) => true,
+            Value::Builtin(_) => true,
+            Value::MultiMethod(_) => true,
+            Value::Promise(_) => true,
+            Value::Atom(_) => true,
+            Value::Ok(_) => true,
+            Value::Err(_) => true,
+            Value::Record(_) => true,
+            Value::Keyword(_) => true,
             Value::Nil => false,
         }
     }
 
     pub fn is_nil(&self) -> bool {
-        match self {
-            Value::Nil => true,
-            _ => false,
-        }
+        matches!(self, Value::Nil)
     }
 
     pub fn to_display_string(&self) -> String {
@@ -44,6 +90,32 @@ impl Value {
             Value::Number(number) => format!("{}", number),
             Value::String(string) => string.clone(),
             Value::Function(function) => format!("#<Function:{}>", function.identifier()),
+            Value::Builtin(builtin) => format!("#<Builtin:{}>", builtin.name()),
+            Value::MultiMethod(multi_method) => {
+                format!("#<MultiMethod:{}>", multi_method.name())
+            }
+            Value::Promise(promise) => match &*promise.state().borrow() {
+                PromiseState::Realized(value) => {
+                    format!("#<Promise:{}>", value.to_display_string())
+                }
+                _ => "#<Promise:pending>".to_string(),
+            },
+            Value::Atom(atom) => format!("#<Atom:{}>", atom.value().to_display_string()),
+            Value::Ok(value) => format!("#<Ok:{}>", value.to_display_string()),
+            Value::Err(value) => format!("#<Err:{}>", value.to_display_string()),
+            Value::Record(record) if record.values.is_empty() => {
+                format!("#{}", record.record_type().name())
+            }
+            Value::Record(record) => format!(
+                "#{}{{{}}}",
+                record.record_type().name(),
+                record
+                    .fields()
+                    .map(|(field, value)| format!(":{} {}", field, value.to_display_string()))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            Value::Keyword(keyword) => format!(":{}", keyword.name()),
             Value::Boolean(value) => format!("{}", value),
             Value::List(value) => {
                 format!(
@@ -55,50 +127,818 @@ impl Value {
                         .join(" ")
                 )
             }
-            Value::Nil => format!("nil"),
+            Value::LazySequence(sequence) => {
+                format!(
+                    "({})",
+                    sequence
+                        .realized_values()
+                        .iter()
+                        .map(|v| v.to_display_string())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
+            Value::Map(map) => {
+                format!(
+                    "{{{}}}",
+                    map.iter()
+                        .map(|(key, value)| format!(
+                            "{} {}",
+                            key.to_display_string(),
+                            value.to_display_string()
+                        ))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
+            Value::Nil => "nil".to_string(),
         }
     }
 }
 
 impl Value {
+    /// The metadata of a function or list, set with `with-meta`.
+    pub fn meta(&self) -> Option<&Rc<Map>> {
+        match self {
+            Value::Function(function) => function.meta(),
+            Value::List(list) => list.meta(),
+            _ => None,
+        }
+    }
+
     pub fn value_type(&self) -> Type {
         match self {
             Value::Number(_) => Type::Number,
             Value::String(_) => Type::String,
             Value::Boolean(_) => Type::Boolean,
             Value::List(_) => Type::List,
+            Value::LazySequence(_) => Type::LazySequence,
+            Value::Map(_) => Type::Map,
 
             Value::Function(_) => Type::Function,
+            Value::Builtin(_) => Type::Function,
+            Value::MultiMethod(_) => Type::Function,
+            Value::Promise(_) => Type::Promise,
+            Value::Atom(_) => Type::Atom,
+            Value::Ok(_) | Value::Err(_) => Type::Result,
+            Value::Record(record) => Type::Record(record.record_type().name().to_string()),
+            Value::Keyword(_) => Type::Keyword,
             Value::Nil => Type::Nil,
         }
     }
 }
 
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Value::Number(number) => number.hash(state),
+            Value::String(string) => string.hash(state),
+            Value::List(values) => values.hash(state),
+            Value::LazySequence(sequence) => Rc::as_ptr(&sequence.state).hash(state),
+            Value::Map(map) => {
+                // Combine the entries in an order independent way, matching
+                // the equality of maps.
+                let combined = map
+                    .iter()
+                    .map(|entry| {
+                        let mut hasher = DefaultHasher::new();
+                        entry.hash(&mut hasher);
+                        hasher.finish()
+                    })
+                    .fold(0u64, |acc, hash| acc.wrapping_add(hash));
+
+                combined.hash(state);
+            }
+            Value::Function(function) => function.identifier().hash(state),
+            Value::Builtin(builtin) => builtin.name().hash(state),
+            Value::MultiMethod(multi_method) => Rc::as_ptr(&multi_method.state).hash(state),
+            Value::Promise(promise) => Rc::as_ptr(&promise.state).hash(state),
+            Value::Atom(atom) => Rc::as_ptr(&atom.state).hash(state),
+            Value::Ok(value) | Value::Err(value) => value.hash(state),
+            Value::Record(record) => {
+                record.record_type().name().hash(state);
+                record.values.hash(state);
+            }
+            Value::Keyword(keyword) => Rc::as_ptr(&keyword.name).hash(state),
+            Value::Boolean(value) => value.hash(state),
+            Value::Nil => (),
+        }
+    }
+}
+
+thread_local! {
+    static KEYWORDS: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// A keyword such as `:name`. Keywords are interned, so that comparing two
+/// of them compares pointers rather than names.
+#[derive(Clone)]
+pub struct Keyword {
+    name: Rc<str>,
+}
+
+impl Keyword {
+    /// The keyword with the name, without its leading colon.
+    pub fn new(name: &str) -> Self {
+        let name = KEYWORDS.with(|keywords| {
+            let mut keywords = keywords.borrow_mut();
+
+            if let Some(name) = keywords.get(name) {
+                return name.clone();
+            }
+
+            let name: Rc<str> = Rc::from(name);
+            keywords.insert(name.clone());
+            name
+        });
+
+        Self { name }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl std::fmt::Debug for Keyword {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Keyword({})", self.name())
+    }
+}
+
+impl PartialEq for Keyword {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.name, &other.name)
+    }
+}
+
+/// A record type declared with `defrecord`, e.g. `(defrecord Point [x y])`,
+/// or a variant of a sum type declared with `deftype`.
+#[derive(Debug, PartialEq)]
+pub struct RecordType {
+    name: String,
+    fields: Vec<String>,
+    sum_type: Option<Rc<SumType>>,
+}
+
+impl RecordType {
+    pub fn new(name: String, fields: Vec<String>) -> Self {
+        Self {
+            name,
+            fields,
+            sum_type: None,
+        }
+    }
+
+    pub fn variant(name: String, fields: Vec<String>, sum_type: Rc<SumType>) -> Self {
+        Self {
+            name,
+            fields,
+            sum_type: Some(sum_type),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn field_names(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// The sum type this is a variant of, if any.
+    pub fn sum_type(&self) -> Option<&SumType> {
+        self.sum_type.as_deref()
+    }
+}
+
+/// A type with a fixed set of variants, e.g. `(deftype Shape (Circle r)
+/// (Rect w h))`.
+#[derive(Debug, PartialEq)]
+pub struct SumType {
+    name: String,
+    variants: Vec<String>,
+}
+
+impl SumType {
+    pub fn new(name: String, variants: Vec<String>) -> Self {
+        Self { name, variants }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn variants(&self) -> &[String] {
+        &self.variants
+    }
+}
+
+/// An instance of a record type. Records are equal if they have the same
+/// type and field values.
 #[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    record_type: Rc<RecordType>,
+    values: Vec<Value>,
+}
+
+impl Record {
+    /// Creates a record with one value per field of `record_type`, in order.
+    pub fn new(record_type: Rc<RecordType>, values: Vec<Value>) -> Self {
+        debug_assert_eq!(record_type.fields.len(), values.len());
+        Self {
+            record_type,
+            values,
+        }
+    }
+
+    pub fn record_type(&self) -> &Rc<RecordType> {
+        &self.record_type
+    }
+
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.record_type
+            .fields
+            .iter()
+            .position(|name| name == field)
+            .map(|index| &self.values[index])
+    }
+
+    /// The fields with their values, in the order they were declared.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.record_type
+            .fields
+            .iter()
+            .map(String::as_str)
+            .zip(self.values.iter())
+    }
+}
+
+pub type Thunk = Box<dyn FnOnce(&mut Interpreter) -> Result<Value, Error>>;
+
+/// A sequence whose elements are computed on demand. Each cell is either
+/// still pending, in which case its thunk produces a sequence value once
+/// realized, or realized into an empty sequence or a first element followed
+/// by the rest of the sequence.
+#[derive(Clone)]
+pub struct LazySequence {
+    state: Rc<RefCell<LazyState>>,
+}
+
+pub enum LazyState {
+    Pending(Thunk),
+    Realizing,
+    Failed(Error),
+    Empty,
+    Cons(Value, Value),
+}
+
+impl LazySequence {
+    pub fn new(thunk: Thunk) -> Self {
+        Self::from_state(LazyState::Pending(thunk))
+    }
+
+    /// An already realized cell, used to put a value in front of another
+    /// sequence without realizing it.
+    pub fn cons(first: Value, rest: Value) -> Self {
+        Self::from_state(LazyState::Cons(first, rest))
+    }
+
+    fn from_state(state: LazyState) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    pub fn state(&self) -> &RefCell<LazyState> {
+        &self.state
+    }
+
+    /// The elements realized so far, followed by `...` if the rest of the
+    /// sequence has not been realized yet.
+    fn realized_values(&self) -> Vec<Value> {
+        let mut values = Vec::new();
+        let mut sequence = self.clone();
+
+        loop {
+            let rest = match &*sequence.state.borrow() {
+                LazyState::Cons(first, rest) => {
+                    values.push(first.clone());
+                    rest.clone()
+                }
+                LazyState::Empty => return values,
+                _ => {
+                    values.push(Value::String("...".to_string()));
+                    return values;
+                }
+            };
+
+            match rest {
+                Value::LazySequence(rest) => sequence = rest,
+                Value::List(rest) => {
+                    values.extend(rest);
+                    return values;
+                }
+                Value::Nil => return values,
+                rest => {
+                    values.push(rest);
+                    return values;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LazySequence {
+    fn drop(&mut self) {
+        // Unlink long chains of realized cells one at a time, since dropping
+        // them recursively could overflow the stack.
+        if Rc::strong_count(&self.state) != 1 {
+            return;
+        }
+
+        let mut rest = match &mut *self.state.borrow_mut() {
+            LazyState::Cons(_, rest) => std::mem::replace(rest, Value::Nil),
+            _ => return,
+        };
+
+        while let Value::LazySequence(sequence) = rest {
+            if Rc::strong_count(&sequence.state) != 1 {
+                break;
+            }
+
+            rest = match &mut *sequence.state.borrow_mut() {
+                LazyState::Cons(_, rest) => std::mem::replace(rest, Value::Nil),
+                _ => Value::Nil,
+            };
+        }
+    }
+}
+
+impl std::fmt::Debug for LazySequence {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "LazySequence({:?})", self.realized_values())
+    }
+}
+
+impl PartialEq for LazySequence {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+/// The result of `(delay expr)`: `expr` is evaluated the first time the
+/// promise is forced and the result is kept for every later `force`.
+#[derive(Clone)]
+pub struct Promise {
+    state: Rc<RefCell<PromiseState>>,
+}
+
+pub enum PromiseState {
+    Pending(Thunk),
+    Realizing,
+    Failed(Error),
+    Realized(Value),
+}
+
+impl Promise {
+    pub fn new(thunk: Thunk) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(PromiseState::Pending(thunk))),
+        }
+    }
+
+    pub fn state(&self) -> &RefCell<PromiseState> {
+        &self.state
+    }
+}
+
+impl std::fmt::Debug for Promise {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Promise({:p})", Rc::as_ptr(&self.state))
+    }
+}
+
+impl PartialEq for Promise {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+/// A mutable reference cell shared by every copy of the value.
+#[derive(Clone)]
+pub struct Atom {
+    state: Rc<RefCell<AtomState>>,
+}
+
+struct AtomState {
+    value: Value,
+    watches: Vec<(Value, Value)>,
+}
+
+impl Atom {
+    pub fn new(value: Value) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(AtomState {
+                value,
+                watches: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn value(&self) -> Value {
+        self.state.borrow().value.clone()
+    }
+
+    /// Replaces the value, returning the previous one.
+    pub fn replace(&self, value: Value) -> Value {
+        std::mem::replace(&mut self.state.borrow_mut().value, value)
+    }
+
+    /// The watch functions by key, in the order they were added.
+    pub fn watches(&self) -> Vec<(Value, Value)> {
+        self.state.borrow().watches.clone()
+    }
+
+    pub fn add_watch(&self, key: Value, function: Value) {
+        self.remove_watch(&key);
+        self.state.borrow_mut().watches.push((key, function));
+    }
+
+    pub fn remove_watch(&self, key: &Value) {
+        self.state
+            .borrow_mut()
+            .watches
+            .retain(|(watch_key, _)| watch_key != key);
+    }
+}
+
+impl std::fmt::Debug for Atom {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Atom({:?})", self.state.borrow().value)
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+/// An insertion-ordered association list. Equality ignores the order in which
+/// the entries were inserted.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(Value, Value)>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn insert(&mut self, key: Value, value: Value) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|(entry_key, _)| *entry_key == key)
+        {
+            entry.1 = value;
+        } else {
+            self.entries.push((key, value));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter()
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+/// The values of a list along with its metadata, which equality and hashing
+/// ignore. Lists are used like the vectors they wrap.
+#[derive(Debug, Clone, Default)]
+pub struct List {
+    values: Vec<Value>,
+    meta: Option<Rc<Map>>,
+}
+
+impl List {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn meta(&self) -> Option<&Rc<Map>> {
+        self.meta.as_ref()
+    }
+
+    pub fn with_meta(mut self, meta: Option<Rc<Map>>) -> Self {
+        self.meta = meta;
+        self
+    }
+}
+
+impl Deref for List {
+    type Target = Vec<Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl DerefMut for List {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.values
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(values: Vec<Value>) -> Self {
+        Self { values, meta: None }
+    }
+}
+
+impl From<List> for Vec<Value> {
+    fn from(list: List) -> Self {
+        list.values
+    }
+}
+
+impl FromIterator<Value> for List {
+    fn from_iter<T: IntoIterator<Item = Value>>(values: T) -> Self {
+        Self::from(values.into_iter().collect::<Vec<Value>>())
+    }
+}
+
+impl IntoIterator for List {
+    type Item = Value;
+    type IntoIter = std::vec::IntoIter<Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a List {
+    type Item = &'a Value;
+    type IntoIter = std::slice::Iter<'a, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.values == other.values
+    }
+}
+
+impl Hash for List {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.values.hash(state);
+    }
+}
+
+#[derive(Clone)]
 pub struct Function {
     identifier: String,
-    parameter_list: Vec<String>,
-    body: ASTNode,
+    /// Shared between clones, which keeps the function and so `Value` small.
+    arities: Rc<Vec<FunctionArity>>,
+    closure: Closure,
+    /// The module the function was declared in, or `None` for the file
+    /// being run.
+    file: Option<Rc<Path>>,
+    /// The metadata of the function, including the docstring of its `defn`
+    /// as `:doc`. Equality ignores it.
+    meta: Option<Rc<Map>>,
 }
 
 impl Function {
-    pub fn new(identifier: String, parameter_list: Vec<String>, body: ASTNode) -> Self {
+    pub fn new(
+        identifier: String,
+        arities: Vec<FunctionArity>,
+        closure: Closure,
+        file: Option<Rc<Path>>,
+    ) -> Self {
         Self {
             identifier,
-            parameter_list,
-            body,
+            arities: Rc::new(arities),
+            closure,
+            file,
+            meta: None,
         }
     }
 
+    pub fn with_meta(mut self, meta: Option<Rc<Map>>) -> Self {
+        self.meta = meta;
+        self
+    }
+
     pub fn identifier(&self) -> &str {
         &self.identifier
     }
 
-    pub fn parameter_list(&self) -> &Vec<String> {
-        &self.parameter_list
+    pub fn meta(&self) -> Option<&Rc<Map>> {
+        self.meta.as_ref()
+    }
+
+    /// The docstring of the `defn` declaring the function, kept in its
+    /// metadata as `:doc`.
+    pub fn doc(&self) -> Option<&str> {
+        match self.meta()?.get(&Value::Keyword(Keyword::new("doc"))) {
+            Some(Value::String(doc)) => Some(doc),
+            _ => None,
+        }
+    }
+
+    pub fn arities(&self) -> &Vec<FunctionArity> {
+        &self.arities
+    }
+
+    /// The environment the function was declared in.
+    pub fn closure(&self) -> &Closure {
+        &self.closure
+    }
+
+    pub fn file(&self) -> Option<&Rc<Path>> {
+        self.file.as_ref()
+    }
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The closure is left out since it may contain the function itself.
+        formatter
+            .debug_struct("Function")
+            .field("identifier", &self.identifier)
+            .field("arities", &self.arities)
+            .finish()
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.identifier == other.identifier
+            && self.arities == other.arities
+            && self.closure == other.closure
+    }
+}
+
+/// How a multimethod picks the method to call.
+pub enum Dispatch {
+    /// By the value a function returns for the arguments, as declared with
+    /// `defmulti`.
+    Function(Value),
+
+    /// By the type of the first argument, as for the methods of a protocol.
+    Type,
+}
+
+/// A function with several implementations, declared with `defmulti` or
+/// `defprotocol`. Methods are added to it later with `defmethod` or
+/// `extend-type`.
+#[derive(Clone)]
+pub struct MultiMethod {
+    state: Rc<MultiMethodState>,
+}
+
+struct MultiMethodState {
+    name: String,
+    dispatch: Dispatch,
+    methods: RefCell<HashMap<Value, Value>>,
+    default: RefCell<Option<Value>>,
+}
+
+impl MultiMethod {
+    pub fn new(name: &str, dispatch: Dispatch) -> Self {
+        Self {
+            state: Rc::new(MultiMethodState {
+                name: name.to_string(),
+                dispatch,
+                methods: RefCell::new(HashMap::new()),
+                default: RefCell::new(None),
+            }),
+        }
     }
 
-    pub fn body(&self) -> &ASTNode {
-        &self.body
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    pub fn dispatch(&self) -> &Dispatch {
+        &self.state.dispatch
+    }
+
+    /// The method for a dispatch value, if one was added.
+    pub fn method(&self, dispatch_value: &Value) -> Option<Value> {
+        self.state.methods.borrow().get(dispatch_value).cloned()
+    }
+
+    /// The method used when there is none for the dispatch value.
+    pub fn default_method(&self) -> Option<Value> {
+        self.state.default.borrow().clone()
+    }
+
+    pub fn add_method(&self, dispatch_value: Value, method: Value) {
+        self.state
+            .methods
+            .borrow_mut()
+            .insert(dispatch_value, method);
+    }
+
+    pub fn set_default_method(&self, method: Value) {
+        self.state.default.replace(Some(method));
+    }
+}
+
+impl std::fmt::Debug for MultiMethod {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "MultiMethod({})", self.name())
+    }
+}
+
+impl PartialEq for MultiMethod {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+pub type BuiltinFunction = fn(&mut Interpreter, Vec<Value>) -> Result<Value, Error>;
+
+type NativeFunction = dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Error>;
+
+/// A function implemented by the interpreter itself, e.g. `add` or `car`, or
+/// a native closure created at runtime, e.g. by `memoize`.
+#[derive(Clone)]
+pub struct Builtin {
+    name: Rc<str>,
+    function: Rc<NativeFunction>,
+}
+
+impl Builtin {
+    pub fn new(name: &str, function: BuiltinFunction) -> Self {
+        Self::from_closure(name, function)
+    }
+
+    pub fn from_closure(
+        name: &str,
+        function: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, Error> + 'static,
+    ) -> Self {
+        Self {
+            name: Rc::from(name),
+            function: Rc::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Error> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+impl std::fmt::Debug for Builtin {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Builtin({})", self.name)
+    }
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
     }
 }